@@ -1,8 +1,12 @@
+pub mod notify_config;
 pub mod printer_config;
 pub mod printer_id;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use printer_config::{PrinterConfigBambu, PrinterConfigKlipper, PrinterConfigPrusa};
+use notify_config::NotifyConfig;
+use printer_config::{
+    PrinterConfigBambu, PrinterConfigKlipper, PrinterConfigOcto, PrinterConfigPrusa,
+};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, trace, warn};
 
@@ -26,6 +30,11 @@ pub struct AppConfig {
 
     ids: Arc<RwLock<HashSet<PrinterId>>>,
     printers: Arc<DashMap<PrinterId, PrinterConfig>>,
+
+    history: HistoryConfig,
+    http: HttpConfig,
+    mqtt: MqttConfig,
+    notify: NotifyConfig,
 }
 
 impl Default for AppConfig {
@@ -49,6 +58,22 @@ impl AppConfig {
         &self.auth_bambu
     }
 
+    pub fn history(&self) -> &HistoryConfig {
+        &self.history
+    }
+
+    pub fn http(&self) -> &HttpConfig {
+        &self.http
+    }
+
+    pub fn mqtt(&self) -> &MqttConfig {
+        &self.mqtt
+    }
+
+    pub fn notify(&self) -> &NotifyConfig {
+        &self.notify
+    }
+
     pub async fn get_token_async(&self) -> Result<Option<crate::auth::bambu_auth::Token>> {
         {
             let token = self.auth_bambu.read().await.get_token_cached();
@@ -61,12 +86,80 @@ impl AppConfig {
     }
 }
 
+/// `[history]` in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// save temperature history to the database so graphs survive a restart
+    pub persist: bool,
+    /// persisted samples older than this are deleted on startup
+    pub retention_days: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            persist: false,
+            retention_days: 7,
+        }
+    }
+}
+
+/// `[http]` in config.toml, the local JSON API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// address to listen on, only localhost by default since there's no auth
+    pub bind: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8780".to_string(),
+        }
+    }
+}
+
+/// `[mqtt]` in config.toml, republishes printer state to a broker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// topics are `<prefix>/<printer id>/...`
+    pub topic_prefix: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            topic_prefix: "printer_watcher".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(default)]
 struct AppConfigLoader {
+    history: HistoryConfig,
+    http: HttpConfig,
+    mqtt: MqttConfig,
+    notify: NotifyConfig,
     bambu: Vec<PrinterConfigBambu>,
     klipper: Vec<PrinterConfigKlipper>,
     prusa: Vec<PrinterConfigPrusa>,
+    octoprint: Vec<PrinterConfigOcto>,
 }
 
 /// save, load
@@ -78,6 +171,11 @@ impl AppConfig {
 
             ids: Arc::new(RwLock::new(HashSet::new())),
             printers: Arc::new(DashMap::new()),
+
+            history: HistoryConfig::default(),
+            http: HttpConfig::default(),
+            mqtt: MqttConfig::default(),
+            notify: NotifyConfig::default(),
         }
     }
 
@@ -87,6 +185,10 @@ impl AppConfig {
         let cfg: AppConfigLoader = toml::from_str(&std::fs::read_to_string(&path)?)?;
 
         let mut out = Self::empty();
+        out.history = cfg.history;
+        out.http = cfg.http;
+        out.mqtt = cfg.mqtt;
+        out.notify = cfg.notify;
 
         let mut new_ids = false;
 
@@ -94,6 +196,7 @@ impl AppConfig {
         debug!("loaded {} bambu printers", cfg.bambu.len());
         debug!("loaded {} klipper printers", cfg.klipper.len());
         debug!("loaded {} prusa printers", cfg.prusa.len());
+        debug!("loaded {} octoprint printers", cfg.octoprint.len());
 
         for cfg in cfg.bambu {
             let id = if cfg.id.is_empty() {
@@ -140,6 +243,21 @@ impl AppConfig {
             );
         }
 
+        for cfg in cfg.octoprint {
+            let id = if cfg.id.is_empty() {
+                let id = PrinterId::generate();
+                new_ids = true;
+                id
+            } else {
+                cfg.id.clone()
+            };
+            out.ids.blocking_write().insert(id.clone());
+            out.printers.insert(
+                id.clone(),
+                PrinterConfig::Octoprint(id, Arc::new(RwLock::new(cfg))),
+            );
+        }
+
         if new_ids {
             out.save_to_file(path)?;
         }
@@ -149,9 +267,14 @@ impl AppConfig {
 
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let mut loader = AppConfigLoader {
+            history: self.history.clone(),
+            http: self.http.clone(),
+            mqtt: self.mqtt.clone(),
+            notify: self.notify.clone(),
             bambu: Vec::new(),
             klipper: Vec::new(),
             prusa: Vec::new(),
+            octoprint: Vec::new(),
         };
 
         for printer in self.printers() {
@@ -174,6 +297,9 @@ impl AppConfig {
                         // .insert(id.to_string(), cfg.blocking_read().clone());
                         .push(cfg.blocking_read().clone());
                 }
+                PrinterConfig::Octoprint(id, cfg) => {
+                    loader.octoprint.push(cfg.blocking_read().clone());
+                }
             }
         }
 
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::notifications::NotifyEvent;
+
+/// `[notify]` in config.toml.
+/// with no channels, every event goes to a desktop notification
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub channels: Vec<ChannelConfig>,
+    /// with no routes, every channel gets every event
+    pub routes: Vec<RouteConfig>,
+    pub rules: Vec<RuleConfig>,
+    pub quiet_hours: QuietHoursConfig,
+}
+
+/// `[notify.quiet_hours]`, errors are always delivered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHoursConfig {
+    pub enabled: bool,
+    pub mode: QuietMode,
+    /// hours of the day, the app uses the wake and bed times from Options instead
+    pub wake: u32,
+    pub bed: u32,
+    /// warn when a print is going to finish during quiet hours
+    pub eta_warning: bool,
+}
+
+impl Default for QuietHoursConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: QuietMode::Batch,
+            wake: 8,
+            bed: 22,
+            eta_warning: true,
+        }
+    }
+}
+
+/// what happens to non-critical notifications during quiet hours
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietMode {
+    /// held and sent as one message per channel at wake time
+    #[default]
+    Batch,
+    /// dropped
+    Suppress,
+}
+
+/// `[[notify.channels]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    /// referenced by routes
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ChannelKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelKind {
+    Desktop,
+    /// POSTs the notification as JSON
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    Ntfy {
+        #[serde(default = "default_ntfy_server")]
+        server: String,
+        topic: String,
+        /// access token, for protected topics
+        token: Option<String>,
+    },
+    Gotify {
+        server: String,
+        /// application token
+        token: String,
+    },
+    Email {
+        host: String,
+        /// defaults to 587 with STARTTLS, or 465 with `tls = true`
+        port: Option<u16>,
+        /// implicit TLS instead of STARTTLS
+        #[serde(default)]
+        tls: bool,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    /// Discord webhook URL
+    Discord {
+        url: String,
+    },
+    /// Slack (or compatible, e.g. Mattermost) incoming webhook URL
+    Slack {
+        url: String,
+    },
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+/// `[[notify.routes]]`, an event is sent to the channels of every route it matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// channel names
+    pub channels: Vec<String>,
+    /// printer ids or names, empty matches every printer
+    #[serde(default)]
+    pub printers: Vec<String>,
+    /// e.g. "print_complete", "printer_error", empty matches every event
+    #[serde(default)]
+    pub events: Vec<NotifyEvent>,
+}
+
+/// `[[notify.rules]]`, extra alerts checked against each printer's state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    #[serde(flatten)]
+    pub kind: RuleKind,
+    /// printer ids or names, empty matches every printer
+    #[serde(default)]
+    pub printers: Vec<String>,
+    /// minimum time between two alerts from this rule for the same printer
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: u64,
+}
+
+fn default_cooldown() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum RuleKind {
+    Paused,
+    FirstLayer,
+    /// progress reached `percent`
+    Progress {
+        percent: f32,
+    },
+    /// less than `minutes` left
+    Eta {
+        minutes: u32,
+    },
+    /// nozzle more than `degrees` away from its target for `seconds` while printing
+    TempDeviation {
+        degrees: f32,
+        seconds: u64,
+    },
+    /// no updates for `seconds`
+    Disconnected {
+        seconds: u64,
+    },
+    /// any AMS unit above `percent` relative humidity, or at humidity level `index`
+    /// or wetter for units that only report the level (1 is the wettest, 5 the driest)
+    AmsHumidity {
+        percent: f32,
+        #[serde(default = "default_ams_index")]
+        index: i64,
+    },
+}
+
+fn default_ams_index() -> i64 {
+    2
+}
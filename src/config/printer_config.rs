@@ -9,6 +9,7 @@ pub enum PrinterType {
     Bambu,
     Klipper,
     Prusa,
+    Octoprint,
 }
 
 #[derive(Debug, Clone)]
@@ -18,7 +19,7 @@ pub enum PrinterConfig {
     Bambu(PrinterId, Arc<RwLock<PrinterConfigBambu>>),
     Klipper(PrinterId, Arc<RwLock<PrinterConfigKlipper>>),
     Prusa(PrinterId, Arc<RwLock<PrinterConfigPrusa>>),
-    // Octoprint(PrinterId, Arc<RwLock<PrinterConfigOcto>>),
+    Octoprint(PrinterId, Arc<RwLock<PrinterConfigOcto>>),
 }
 
 /// getters
@@ -28,7 +29,7 @@ impl PrinterConfig {
             PrinterConfig::Bambu(id, _) => id.clone(),
             PrinterConfig::Klipper(id, _) => id.clone(),
             PrinterConfig::Prusa(id, _) => id.clone(),
-            // PrinterConfig::Octoprint(id, _) => id.clone(),
+            PrinterConfig::Octoprint(id, _) => id.clone(),
         }
     }
 
@@ -37,7 +38,7 @@ impl PrinterConfig {
             PrinterConfig::Bambu(_, config) => config.read().await.name.clone(),
             PrinterConfig::Klipper(_, config) => config.read().await.name.clone(),
             PrinterConfig::Prusa(_, config) => config.read().await.name.clone(),
-            // PrinterConfig::Octoprint(_, config) => &config.read().await.name,
+            PrinterConfig::Octoprint(_, config) => config.read().await.name.clone(),
         }
     }
 
@@ -46,7 +47,7 @@ impl PrinterConfig {
             PrinterConfig::Bambu(_, config) => config.blocking_read().name.clone(),
             PrinterConfig::Klipper(_, config) => config.blocking_read().name.clone(),
             PrinterConfig::Prusa(_, config) => config.blocking_read().name.clone(),
-            // PrinterConfig::Octoprint(_, config) => &config.read().await.name,
+            PrinterConfig::Octoprint(_, config) => config.blocking_read().name.clone(),
         }
     }
 
@@ -55,7 +56,7 @@ impl PrinterConfig {
             PrinterConfig::Bambu(_, _) => PrinterType::Bambu,
             PrinterConfig::Klipper(_, _) => PrinterType::Klipper,
             PrinterConfig::Prusa(_, _) => PrinterType::Prusa,
-            // PrinterConfig::Octoprint(_, _) => PrinterType::Octoprint,
+            PrinterConfig::Octoprint(_, _) => PrinterType::Octoprint,
         }
     }
 }
@@ -142,14 +143,29 @@ pub struct PrinterConfigPrusa {
     // pub fingerprint: String,
     // pub token: String,
     pub octo: Option<PrinterConfigOcto>,
+    /// an XL with more than one tool, enables the tool menus
+    #[serde(default)]
+    pub toolchanger: bool,
     #[cfg(feature = "rtsp")]
     pub rtsp: Option<crate::streaming::rtsp::RtspCreds>,
 }
 
+/// `[[octoprint]]`, or `octo` on a Prusa printer where id and name aren't used
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrinterConfigOcto {
-    // pub id: PrinterId,
-    // pub name: String,
+    #[serde(default = "PrinterId::empty")]
+    pub id: PrinterId,
+    #[serde(default)]
+    pub name: String,
     pub host: String,
+    #[serde(default = "PrinterConfigOcto::default_port")]
+    pub port: u16,
+    /// API key
     pub token: String,
 }
+
+impl PrinterConfigOcto {
+    fn default_port() -> u16 {
+        5000
+    }
+}
@@ -8,8 +8,12 @@ use tokio::sync::RwLock;
 use crate::{
     config::{printer_config::PrinterConfigBambu, printer_id::PrinterId},
     conn_manager::{
-        conn_bambu::{command::Command, message::Message},
+        conn_bambu::{
+            command::{Command, CommandSequence},
+            message::Message,
+        },
         worker_message::WorkerMsg,
+        CommandResult,
     },
     status::{
         bambu_status::{BambuPrinterType, PrinterStateBambu},
@@ -25,6 +29,8 @@ pub(super) struct BambuListener {
     pub(super) topic_device_report: String,
     pub(super) topic_device_request: String,
     printer_states: HashMap<PrinterId, PrinterStateBambu>,
+    /// shared with BambuClient
+    sequence: Arc<CommandSequence>,
 }
 
 impl BambuListener {
@@ -35,6 +41,7 @@ impl BambuListener {
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
         topic_device_report: String,
         topic_device_request: String,
+        sequence: Arc<CommandSequence>,
     ) -> Self {
         Self {
             printer_cfg,
@@ -44,6 +51,7 @@ impl BambuListener {
             topic_device_report,
             topic_device_request,
             printer_states: HashMap::new(),
+            sequence,
         }
     }
 
@@ -122,6 +130,7 @@ impl BambuListener {
                     // debug!("incoming publish: {:?}", msg);
 
                     let id = self.printer_cfg.read().await.id.clone();
+                    self.check_reply(&id, &msg)?;
                     // self.tx.send((id, WorkerMsg::from_bambu(msg)?))?;
                     match &msg {
                         Message::Print(print) => {
@@ -151,8 +160,52 @@ impl BambuListener {
         }
     }
 
+    /// match replies to commands sent by BambuClient
+    fn check_reply(&self, id: &PrinterId, msg: &Message) -> Result<()> {
+        let (sequence_id, result, reason) = match msg {
+            Message::Print(print)
+                if print
+                    .print
+                    .command
+                    .as_ref()
+                    .is_some_and(|c| c != "push_status") =>
+            {
+                (
+                    print.print.sequence_id.as_ref(),
+                    print.print.result.as_ref(),
+                    print.print.reason.as_ref(),
+                )
+            }
+            Message::System(system) => (
+                Some(&system.system.sequence_id),
+                Some(&system.system.result),
+                None,
+            ),
+            Message::Info(info) => (
+                Some(&info.info.sequence_id),
+                info.info.result.as_ref(),
+                info.info.reason.as_ref(),
+            ),
+            _ => return Ok(()),
+        };
+
+        let Some(cmd_id) = sequence_id.and_then(|s| self.sequence.take_reply(s)) else {
+            return Ok(());
+        };
+
+        let result = match result.map(|r| r.to_lowercase()) {
+            None => CommandResult::Acked,
+            Some(r) if r == "success" => CommandResult::Acked,
+            Some(r) => CommandResult::Rejected(reason.cloned().unwrap_or(r)),
+        };
+
+        self.tx
+            .send((id.clone(), WorkerMsg::CommandResult(cmd_id, result)))?;
+        Ok(())
+    }
+
     pub(super) async fn send_get_version(&mut self) -> Result<()> {
-        let payload = Command::GetVersion.get_payload();
+        let payload = Command::GetVersion.get_payload(self.sequence.next_id())?;
 
         self.client
             .publish(
@@ -168,7 +221,7 @@ impl BambuListener {
 
     pub(super) async fn send_pushall(&mut self) -> Result<()> {
         let command = Command::PushAll;
-        let payload = command.get_payload();
+        let payload = command.get_payload(self.sequence.next_id())?;
 
         let qos = rumqttc::QoS::AtMostOnce;
         self.client
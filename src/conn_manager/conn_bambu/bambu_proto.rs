@@ -12,10 +12,14 @@ use tokio::sync::RwLock;
 use crate::{
     auth::bambu_auth::AuthDb,
     config::{printer_config::PrinterConfigBambu, printer_id::PrinterId, AppConfig},
-    conn_manager::{worker_message::WorkerMsg, WorkerCmd},
+    conn_manager::{worker_message::WorkerMsg, CommandId, CommandResult, WorkerCmd},
 };
 
-use super::{bambu_listener::BambuListener, command::Command, message::Message};
+use super::{
+    bambu_listener::BambuListener,
+    command::{Command, CommandSequence, ProjectFile},
+    message::Message,
+};
 
 /// scary, insecure, do not allow outside of local network
 #[derive(Debug)]
@@ -83,9 +87,11 @@ pub struct BambuClient {
     config: Arc<RwLock<PrinterConfigBambu>>,
     client: rumqttc::AsyncClient,
     tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
     topic_device_request: String,
     topic_device_report: String,
+    /// replies are matched by the listener
+    sequence: Arc<CommandSequence>,
 }
 
 impl BambuClient {
@@ -94,7 +100,7 @@ impl BambuClient {
         config: AppConfig,
         printer_cfg: Arc<RwLock<PrinterConfigBambu>>,
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
         kill_rx: tokio::sync::oneshot::Receiver<()>,
     ) -> Result<Self> {
         if config.logged_in() {
@@ -108,7 +114,7 @@ impl BambuClient {
         auth: Arc<RwLock<AuthDb>>,
         printer_cfg: Arc<RwLock<PrinterConfigBambu>>,
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
         kill_rx: tokio::sync::oneshot::Receiver<()>,
     ) -> Result<Self> {
         debug!("init cloud mqtt listener");
@@ -152,6 +158,7 @@ impl BambuClient {
             cmd_rx,
             topic_device_request: format!("device/{}/request", &printer_cfg.read().await.serial),
             topic_device_report: format!("device/{}/report", &printer_cfg.read().await.serial),
+            sequence: Arc::new(CommandSequence::default()),
         };
 
         out.init(eventloop, kill_rx).await?;
@@ -162,7 +169,7 @@ impl BambuClient {
     async fn _new_and_init_lan(
         printer_cfg: Arc<RwLock<PrinterConfigBambu>>,
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
         kill_rx: tokio::sync::oneshot::Receiver<()>,
     ) -> Result<Self> {
         debug!("init lan mqtt listener");
@@ -204,6 +211,7 @@ impl BambuClient {
             cmd_rx,
             topic_device_request: format!("device/{}/request", &printer.serial),
             topic_device_report: format!("device/{}/report", &printer.serial),
+            sequence: Arc::new(CommandSequence::default()),
         };
         out.init(eventloop, kill_rx).await?;
 
@@ -220,6 +228,7 @@ impl BambuClient {
         let tx2 = self.tx.clone();
         let topic_report = self.topic_device_report.clone();
         let topic_request = self.topic_device_request.clone();
+        let sequence = self.sequence.clone();
 
         // if let Err(e) = self.publish(Command::GetVersion).await {
         //     error!("Error publishing command: {:?}", e);
@@ -233,6 +242,7 @@ impl BambuClient {
                 tx2,
                 topic_report,
                 topic_request,
+                sequence,
             );
 
             loop {
@@ -262,8 +272,75 @@ impl BambuClient {
         Ok(())
     }
 
-    pub async fn publish(&self, command: Command) -> Result<()> {
-        let payload = command.get_payload();
+    /// forward worker commands to the printer until the manager drops the channel
+    pub async fn run(&mut self) -> Result<()> {
+        while let Some((cmd_id, cmd)) = self.cmd_rx.recv().await {
+            debug!("got worker command: {:?}", cmd);
+            if let Err(e) = self.handle_command(cmd_id, cmd).await {
+                error!("error handling command: {:?}", e);
+                self.sequence.forget(cmd_id);
+                let id = self.config.read().await.id.clone();
+                self.tx.send((
+                    id,
+                    WorkerMsg::CommandResult(cmd_id, CommandResult::Rejected(e.to_string())),
+                ))?;
+            }
+        }
+        debug!("command channel closed");
+        Ok(())
+    }
+
+    async fn handle_command(&mut self, cmd_id: CommandId, cmd: WorkerCmd) -> Result<()> {
+        let command = match cmd {
+            WorkerCmd::Pause => Command::Pause,
+            WorkerCmd::Resume => Command::Resume,
+            WorkerCmd::Cancel => Command::Stop,
+            WorkerCmd::SetNozzleTemp(Some(tool), temp) => {
+                Command::SendGCodeTemplate(format!("M104 T{} S{:.0}\n", tool, temp))
+            }
+            WorkerCmd::SetNozzleTemp(None, temp) => {
+                Command::SendGCodeTemplate(format!("M104 S{:.0}\n", temp))
+            }
+            WorkerCmd::SetBedTemp(temp) => {
+                Command::SendGCodeTemplate(format!("M140 S{:.0}\n", temp))
+            }
+            WorkerCmd::SetFan(speed) => {
+                /// P1 = part cooling fan
+                let speed = (speed.clamp(0., 1.) * 255.).round() as u8;
+                Command::SendGCodeTemplate(format!("M106 P1 S{}\n", speed))
+            }
+            WorkerCmd::SetLight(on) => Command::SetChamberLight(on),
+            WorkerCmd::SetAmsFilament(setting) => Command::ChangeAMSFilamentSetting(setting),
+            WorkerCmd::PrintFile(path) => Command::ProjectFile(ProjectFile::new(&path)),
+            WorkerCmd::PrintProjectFile(file) => Command::ProjectFile(file),
+            WorkerCmd::Gcode(gcode) => {
+                let mut gcode = gcode.trim_end().to_string();
+                gcode.push('\n');
+                Command::SendGCodeTemplate(gcode)
+            }
+            WorkerCmd::EmergencyStop | WorkerCmd::FirmwareRestart => {
+                bail!("{:?} not supported on bambu", cmd)
+            }
+            WorkerCmd::Toolchanger(_) => bail!("toolchanger not supported on bambu"),
+            WorkerCmd::Motion(cmd) => {
+                let mut gcode = cmd.to_gcode().join("\n");
+                gcode.push('\n');
+                Command::SendGCodeTemplate(gcode)
+            }
+        };
+        let sequence_id = self.sequence.register(cmd_id);
+        self.publish_with_id(command, sequence_id).await
+    }
+
+    /// returns the sequence_id the command was sent with
+    pub async fn publish(&self, command: Command) -> Result<u64> {
+        let sequence_id = self.sequence.next_id();
+        self.publish_with_id(command, sequence_id).await?;
+        Ok(sequence_id)
+    }
+
+    async fn publish_with_id(&self, command: Command, sequence_id: u64) -> Result<()> {
+        let payload = command.get_payload(sequence_id)?;
 
         let qos = rumqttc::QoS::AtMostOnce;
         self.client
@@ -1,4 +1,15 @@
-// TODO: increment sequence command?
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::conn_manager::{CommandId, COMMAND_TIMEOUT};
+
 #[derive(Debug, Clone)]
 pub enum Command {
     /// Get the version of the printer.
@@ -17,75 +28,464 @@ pub enum Command {
     SendGCodeTemplate(String),
     GetAccessories,
     ChangeAMSFilamentSetting(ChangeAMSFilamentSetting),
+    ProjectFile(ProjectFile),
 }
 
-#[derive(Debug, Clone)]
+/// start printing a sliced .3mf that's already on the SD card, e.g. uploaded over FTPS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectFile {
+    /// path on the SD card, e.g. "/cube.gcode.3mf"
+    pub path: String,
+    /// 1-based, the file has to contain the sliced gcode for this plate
+    #[serde(default = "ProjectFile::default_plate")]
+    pub plate: u32,
+    /// global AMS tray (ams_id * 4 + tray_id) for each filament in the file,
+    /// empty to print from the external spool
+    #[serde(default)]
+    pub ams_mapping: Vec<i64>,
+    #[serde(default = "ProjectFile::default_bed_leveling")]
+    pub bed_leveling: bool,
+    #[serde(default)]
+    pub timelapse: bool,
+}
+
+impl ProjectFile {
+    fn default_plate() -> u32 {
+        1
+    }
+
+    fn default_bed_leveling() -> bool {
+        true
+    }
+
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            plate: 1,
+            ams_mapping: vec![],
+            bed_leveling: true,
+            timelapse: false,
+        }
+    }
+
+    /// file name without the extension, shown as the task name on the printer
+    pub fn subtask_name(&self) -> &str {
+        let name = self.path.rsplit('/').next().unwrap_or(&self.path);
+        name.strip_suffix(".gcode.3mf")
+            .or_else(|| name.strip_suffix(".3mf"))
+            .unwrap_or(name)
+    }
+}
+
+/// set the filament info for an AMS tray, or the external spool (ams_id 255)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChangeAMSFilamentSetting {
-    ams_id: i64,
-    tray_id: i64,
-    tray_color: [u8; 3],
-    nozzle_temp_min: i64,
-    nozzle_temp_max: i64,
-    tray_type: String,
+    pub ams_id: i64,
+    pub tray_id: i64,
+    /// filament preset id, e.g. "GFL99" for Generic PLA
+    pub tray_info_idx: String,
+    pub tray_color: [u8; 3],
+    pub nozzle_temp_min: i64,
+    pub nozzle_temp_max: i64,
+    pub tray_type: String,
 }
 
-impl Command {
-    pub(crate) fn get_payload(&self) -> String {
-        match self {
-            Self::GetVersion => GET_VERSION_PAYLOD.into(),
-            Self::Pause => PAUSE_PAYLOAD.into(),
-            Self::Resume => RESUME_PAYLOAD.into(),
-            Self::Stop => STOP_PAYLOAD.into(),
-            Self::PushAll => PUSHALL_PAYLOAD.into(),
-            Self::StartPush => START_PUSH_PAYLOAD.into(),
-            Self::SetChamberLight(on) => {
-                SET_CHAMBER_LIGHT_PAYLOAD.replace("<LED_STATUS>", if *on { "on" } else { "off" })
-            }
-            Self::SetSpeedProfile(profile) => {
-                SET_SPEED_PROFILE_PAYLOAD.replace("<PROFILE>", profile)
-            }
-            Self::SendGCodeTemplate(gcode) => SEND_GCODE_TEMPLATE_PAYLOAD.replace("<GCODE>", gcode),
-            Self::GetAccessories => GET_ACCESSORIES_PAYLOAD.into(),
-            Self::ChangeAMSFilamentSetting(setting) => {
-                // format!(
-                //     r#"
-                //     "print": {{
-                //         "sequence_id": "0",
-                //         "command": "ams_filament_setting",
-                //         "ams_id": {},
-                //         "tray_id": {},
-                //         "tray_info_idx": "",
-                //         "tray_color": "{:02X}{:02X}{:02X}",
-                //         "nozzle_temp_min": {},
-                //         "nozzle_temp_max": {},
-                //         "tray_type": "{}"
-                //     }}
-                //     "#,
-                //     setting.ams_id,
-                //     setting.tray_id,
-                //     setting.tray_color[0],
-                //     setting.tray_color[1],
-                //     setting.tray_color[2],
-                //     setting.nozzle_temp_min,
-                //     setting.nozzle_temp_max,
-                //     setting.tray_type,
-                // )
-                panic!("ChangeAMSFilamentSetting is not implemented");
-            }
+impl ChangeAMSFilamentSetting {
+    /// ams_id used for the external spool
+    pub const EXTERNAL_AMS_ID: i64 = 255;
+}
+
+/// filament presets built into the printer firmware
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilamentPreset {
+    pub name: &'static str,
+    pub tray_info_idx: &'static str,
+    pub tray_type: &'static str,
+    pub nozzle_temp_min: i64,
+    pub nozzle_temp_max: i64,
+}
+
+impl FilamentPreset {
+    const fn new(
+        name: &'static str,
+        tray_info_idx: &'static str,
+        tray_type: &'static str,
+        nozzle_temp_min: i64,
+        nozzle_temp_max: i64,
+    ) -> Self {
+        Self {
+            name,
+            tray_info_idx,
+            tray_type,
+            nozzle_temp_min,
+            nozzle_temp_max,
+        }
+    }
+
+    pub fn find(tray_info_idx: &str) -> Option<&'static Self> {
+        FILAMENT_PRESETS
+            .iter()
+            .find(|p| p.tray_info_idx == tray_info_idx)
+    }
+}
+
+pub static FILAMENT_PRESETS: &[FilamentPreset] = &[
+    FilamentPreset::new("Generic PLA", "GFL99", "PLA", 190, 240),
+    FilamentPreset::new("Generic PLA-CF", "GFL98", "PLA-CF", 190, 240),
+    FilamentPreset::new("Generic PLA Silk", "GFL96", "PLA", 190, 240),
+    FilamentPreset::new("Generic PETG", "GFG99", "PETG", 220, 270),
+    FilamentPreset::new("Generic PETG-CF", "GFG98", "PETG-CF", 240, 270),
+    FilamentPreset::new("Generic ABS", "GFB99", "ABS", 240, 270),
+    FilamentPreset::new("Generic ASA", "GFB98", "ASA", 240, 270),
+    FilamentPreset::new("Generic TPU", "GFU99", "TPU", 200, 250),
+    FilamentPreset::new("Generic PA", "GFN99", "PA", 260, 290),
+    FilamentPreset::new("Generic PA-CF", "GFN98", "PA-CF", 260, 290),
+    FilamentPreset::new("Generic PC", "GFC99", "PC", 260, 280),
+    FilamentPreset::new("Generic PVA", "GFS99", "PVA", 220, 250),
+    FilamentPreset::new("Generic HIPS", "GFS98", "HIPS", 220, 270),
+    FilamentPreset::new("Bambu PLA Basic", "GFA00", "PLA", 190, 230),
+    FilamentPreset::new("Bambu PLA Matte", "GFA01", "PLA", 190, 230),
+    FilamentPreset::new("Bambu PETG HF", "GFG02", "PETG", 230, 260),
+];
+
+/// numbers outgoing commands and tracks the ones awaiting a reply,
+/// shared between a BambuClient and its listener
+#[derive(Debug)]
+pub struct CommandSequence {
+    next_id: AtomicU64,
+    /// sequence_id -> command, and when it was sent
+    pending: DashMap<String, (CommandId, Instant)>,
+}
+
+impl Default for CommandSequence {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            pending: DashMap::new(),
         }
     }
 }
 
-static GET_VERSION_PAYLOD: &str = r#"{"info": {"sequence_id": "0", "command": "get_version"}}"#;
-static PAUSE_PAYLOAD: &str = r#"{"print": {"sequence_id": "0", "command": "pause"}}"#;
-static RESUME_PAYLOAD: &str = r#"{"print": {"sequence_id": "0", "command": "resume"}}"#;
-static STOP_PAYLOAD: &str = r#"{"print": {"sequence_id": "0", "command": "stop"}}"#;
-static PUSHALL_PAYLOAD: &str = r#"{"pushing": {"sequence_id": "0", "command": "pushall"}}"#;
-static START_PUSH_PAYLOAD: &str = r#"{"pushing": {"sequence_id": "0", "command": "start"}}"#;
-static SET_CHAMBER_LIGHT_PAYLOAD: &str = r#"{"system": {"sequence_id": "0", "command": "ledctrl", "led_node": "chamber_light", "led_mode": "<LED_STATUS>", "led_on_time": 500, "led_off_time": 500, "loop_times": 0, "interval_time": 0}}"#;
-static SET_SPEED_PROFILE_PAYLOAD: &str =
-    r#"{"print": {"sequence_id": "0", "command": "print_speed", "param": "<PROFILE>"}}"#;
-static SEND_GCODE_TEMPLATE_PAYLOAD: &str =
-    r#"{"print": {"sequence_id": "0", "command": "gcode_line", "param": "<GCODE>"}}"#;
-static GET_ACCESSORIES_PAYLOAD: &str =
-    r#"{"system": {"sequence_id": "0", "command": "get_accessories", "accessory_type": "none"}}"#;
+impl CommandSequence {
+    pub fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// registered before sending so a fast reply can't be missed
+    pub fn register(&self, cmd_id: CommandId) -> u64 {
+        self.prune(COMMAND_TIMEOUT);
+        let sequence_id = self.next_id();
+        self.pending
+            .insert(sequence_id.to_string(), (cmd_id, Instant::now()));
+        sequence_id
+    }
+
+    pub fn take_reply(&self, sequence_id: &str) -> Option<CommandId> {
+        self.pending
+            .remove(sequence_id)
+            .map(|(_, (cmd_id, _))| cmd_id)
+    }
+
+    pub fn forget(&self, cmd_id: CommandId) {
+        self.pending.retain(|_, (v, _)| *v != cmd_id);
+    }
+
+    /// drops commands the printer never answered, the manager has already timed them out
+    fn prune(&self, max_age: Duration) {
+        let now = Instant::now();
+        self.pending
+            .retain(|_, (_, sent)| now.duration_since(*sent) <= max_age);
+    }
+}
+
+/// `{"<topic>": {"sequence_id": "..", "command": "..", ..params}}`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Payload<'a> {
+    Print(PayloadBody<'a>),
+    Info(PayloadBody<'a>),
+    Pushing(PayloadBody<'a>),
+    System(PayloadBody<'a>),
+}
+
+#[derive(Debug, Serialize)]
+struct PayloadBody<'a> {
+    sequence_id: String,
+    command: &'a str,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    params: Option<PayloadParams<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum PayloadParams<'a> {
+    Param {
+        param: &'a str,
+    },
+    Led {
+        led_node: &'a str,
+        led_mode: &'a str,
+        led_on_time: i64,
+        led_off_time: i64,
+        loop_times: i64,
+        interval_time: i64,
+    },
+    Accessories {
+        accessory_type: &'a str,
+    },
+    AmsFilament {
+        ams_id: i64,
+        tray_id: i64,
+        tray_info_idx: &'a str,
+        /// RRGGBBAA
+        tray_color: String,
+        nozzle_temp_min: i64,
+        nozzle_temp_max: i64,
+        tray_type: &'a str,
+    },
+    ProjectFile {
+        /// gcode inside the archive, "Metadata/plate_1.gcode"
+        param: String,
+        url: String,
+        subtask_name: &'a str,
+        /// only used by the cloud, the printer accepts "0"
+        project_id: &'a str,
+        profile_id: &'a str,
+        task_id: &'a str,
+        subtask_id: &'a str,
+        md5: &'a str,
+        bed_type: &'a str,
+        timelapse: bool,
+        bed_levelling: bool,
+        flow_cali: bool,
+        vibration_cali: bool,
+        layer_inspect: bool,
+        use_ams: bool,
+        ams_mapping: &'a [i64],
+    },
+}
+
+impl Command {
+    pub(crate) fn get_payload(&self, sequence_id: u64) -> Result<String> {
+        let body = |command, params| PayloadBody {
+            sequence_id: sequence_id.to_string(),
+            command,
+            params,
+        };
+
+        let payload = match self {
+            Self::GetVersion => Payload::Info(body("get_version", None)),
+            Self::Pause => Payload::Print(body("pause", None)),
+            Self::Resume => Payload::Print(body("resume", None)),
+            Self::Stop => Payload::Print(body("stop", None)),
+            Self::PushAll => Payload::Pushing(body("pushall", None)),
+            Self::StartPush => Payload::Pushing(body("start", None)),
+            Self::SetChamberLight(on) => Payload::System(body(
+                "ledctrl",
+                Some(PayloadParams::Led {
+                    led_node: "chamber_light",
+                    led_mode: if *on { "on" } else { "off" },
+                    led_on_time: 500,
+                    led_off_time: 500,
+                    loop_times: 0,
+                    interval_time: 0,
+                }),
+            )),
+            Self::SetSpeedProfile(profile) => Payload::Print(body(
+                "print_speed",
+                Some(PayloadParams::Param { param: profile }),
+            )),
+            Self::SendGCodeTemplate(gcode) => Payload::Print(body(
+                "gcode_line",
+                Some(PayloadParams::Param { param: gcode }),
+            )),
+            Self::GetAccessories => Payload::System(body(
+                "get_accessories",
+                Some(PayloadParams::Accessories {
+                    accessory_type: "none",
+                }),
+            )),
+            Self::ChangeAMSFilamentSetting(setting) => Payload::Print(body(
+                "ams_filament_setting",
+                Some(PayloadParams::AmsFilament {
+                    ams_id: setting.ams_id,
+                    tray_id: setting.tray_id,
+                    tray_info_idx: &setting.tray_info_idx,
+                    tray_color: format!(
+                        "{:02X}{:02X}{:02X}FF",
+                        setting.tray_color[0], setting.tray_color[1], setting.tray_color[2]
+                    ),
+                    nozzle_temp_min: setting.nozzle_temp_min,
+                    nozzle_temp_max: setting.nozzle_temp_max,
+                    tray_type: &setting.tray_type,
+                }),
+            )),
+            Self::ProjectFile(file) => Payload::Print(body(
+                "project_file",
+                Some(PayloadParams::ProjectFile {
+                    param: format!("Metadata/plate_{}.gcode", file.plate),
+                    url: format!("ftp://{}", file.path),
+                    subtask_name: file.subtask_name(),
+                    project_id: "0",
+                    profile_id: "0",
+                    task_id: "0",
+                    subtask_id: "0",
+                    md5: "",
+                    bed_type: "auto",
+                    timelapse: file.timelapse,
+                    bed_levelling: file.bed_leveling,
+                    flow_cali: false,
+                    vibration_cali: false,
+                    layer_inspect: false,
+                    use_ams: !file.ams_mapping.is_empty(),
+                    ams_mapping: &file.ams_mapping,
+                }),
+            )),
+        };
+
+        Ok(serde_json::to_string(&payload)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn payload(cmd: Command, sequence_id: u64) -> Value {
+        serde_json::from_str(&cmd.get_payload(sequence_id).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn gcode_is_escaped() {
+        let gcode = "M117 \"quoted\" \\ done\nG28\r\nM400\n";
+        let v = payload(Command::SendGCodeTemplate(gcode.to_string()), 7);
+        assert_eq!(
+            v,
+            json!({
+                "print": {
+                    "sequence_id": "7",
+                    "command": "gcode_line",
+                    "param": gcode,
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn sequence_ids_increase() {
+        let seq = CommandSequence::default();
+        let a = seq.next_id();
+        let b = seq.register(CommandId::next());
+        let c = seq.register(CommandId::next());
+        let d = seq.next_id();
+        assert!(a < b && b < c && c < d);
+    }
+
+    #[test]
+    fn take_reply_matches_registered() {
+        let seq = CommandSequence::default();
+        let first = CommandId::next();
+        let second = CommandId::next();
+        let a = seq.register(first);
+        let b = seq.register(second);
+
+        assert_eq!(seq.take_reply(&b.to_string()), Some(second));
+        assert_eq!(seq.take_reply(&b.to_string()), None);
+        assert_eq!(seq.take_reply("12345"), None);
+        assert_eq!(seq.take_reply(&a.to_string()), Some(first));
+    }
+
+    #[test]
+    fn forget_and_prune() {
+        let seq = CommandSequence::default();
+        let forgotten = CommandId::next();
+        let a = seq.register(forgotten);
+        seq.forget(forgotten);
+        assert_eq!(seq.take_reply(&a.to_string()), None);
+
+        let b = seq.register(CommandId::next());
+        seq.prune(COMMAND_TIMEOUT);
+        assert!(seq.pending.contains_key(&b.to_string()));
+
+        std::thread::sleep(Duration::from_millis(5));
+        seq.prune(Duration::ZERO);
+        assert!(seq.pending.is_empty());
+    }
+
+    #[test]
+    fn ams_filament_setting_payload() {
+        let setting = ChangeAMSFilamentSetting {
+            ams_id: 1,
+            tray_id: 2,
+            tray_info_idx: "GFL99".to_string(),
+            tray_color: [0x12, 0xAB, 0x00],
+            nozzle_temp_min: 190,
+            nozzle_temp_max: 240,
+            tray_type: "PLA".to_string(),
+        };
+        let v = payload(Command::ChangeAMSFilamentSetting(setting), 3);
+        assert_eq!(
+            v,
+            json!({
+                "print": {
+                    "sequence_id": "3",
+                    "command": "ams_filament_setting",
+                    "ams_id": 1,
+                    "tray_id": 2,
+                    "tray_info_idx": "GFL99",
+                    "tray_color": "12AB00FF",
+                    "nozzle_temp_min": 190,
+                    "nozzle_temp_max": 240,
+                    "tray_type": "PLA",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn project_file_payload() {
+        let file = ProjectFile {
+            path: "/cache/my part.gcode.3mf".to_string(),
+            plate: 2,
+            ams_mapping: vec![0, -1, 5],
+            bed_leveling: false,
+            timelapse: true,
+        };
+        let v = payload(Command::ProjectFile(file), 11);
+        assert_eq!(
+            v,
+            json!({
+                "print": {
+                    "sequence_id": "11",
+                    "command": "project_file",
+                    "param": "Metadata/plate_2.gcode",
+                    "url": "ftp:///cache/my part.gcode.3mf",
+                    "subtask_name": "my part",
+                    "project_id": "0",
+                    "profile_id": "0",
+                    "task_id": "0",
+                    "subtask_id": "0",
+                    "md5": "",
+                    "bed_type": "auto",
+                    "timelapse": true,
+                    "bed_levelling": false,
+                    "flow_cali": false,
+                    "vibration_cali": false,
+                    "layer_inspect": false,
+                    "use_ams": true,
+                    "ams_mapping": [0, -1, 5],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn project_file_external_spool() {
+        let v = payload(Command::ProjectFile(ProjectFile::new("/cube.3mf")), 1);
+        assert_eq!(v["print"]["use_ams"], json!(false));
+        assert_eq!(v["print"]["ams_mapping"], json!([]));
+        assert_eq!(v["print"]["param"], json!("Metadata/plate_1.gcode"));
+        assert_eq!(v["print"]["subtask_name"], json!("cube"));
+    }
+}
@@ -35,6 +35,11 @@ impl ErrorMap {
         None
     }
 
+    /// by `HmsEntry::ecode`
+    pub fn get_hms(&self, ecode: u64) -> Option<&str> {
+        self.hms.get(&ecode).map(|s| s.as_str())
+    }
+
     pub async fn read_or_fetch() -> Result<Self> {
         if let Ok(errors) = Self::read_error_codes() {
             Ok(errors)
@@ -0,0 +1,201 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Health Management System entry from `print.hms`, e.g. "nozzle clog".
+/// the printer repeats the whole list in every full report until the problem clears.
+/// see also:
+/// https://github.com/greghesp/ha-bambulab/blob/main/custom_components/bambu_lab/pybambu/const.py
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HmsEntry {
+    pub attr: u32,
+    pub code: u32,
+}
+
+impl HmsEntry {
+    /// 16 hex digits, the `ErrorMap` key
+    pub fn ecode(&self) -> u64 {
+        ((self.attr as u64) << 32) | self.code as u64
+    }
+
+    /// as shown on the printer and in the Bambu wiki, e.g. "0300_1200_0002_0001"
+    pub fn code_str(&self) -> String {
+        format!(
+            "{:04X}_{:04X}_{:04X}_{:04X}",
+            self.attr >> 16,
+            self.attr & 0xFFFF,
+            self.code >> 16,
+            self.code & 0xFFFF,
+        )
+    }
+
+    pub fn severity(&self) -> HmsSeverity {
+        match self.code >> 16 {
+            1 => HmsSeverity::Fatal,
+            2 => HmsSeverity::Serious,
+            3 => HmsSeverity::Common,
+            4 => HmsSeverity::Info,
+            _ => HmsSeverity::Unknown,
+        }
+    }
+
+    pub fn module(&self) -> HmsModule {
+        match self.attr >> 24 {
+            0x03 => HmsModule::MotionController,
+            0x05 => HmsModule::Mainboard,
+            0x07 => HmsModule::Ams,
+            0x08 => HmsModule::Toolhead,
+            0x0C => HmsModule::Xcam,
+            m => HmsModule::Unknown(m as u8),
+        }
+    }
+
+    /// the AMS unit for AMS entries, counting from 0
+    pub fn ams_id(&self) -> Option<u32> {
+        (self.module() == HmsModule::Ams).then_some((self.attr >> 16) & 0xFF)
+    }
+}
+
+/// for `print.hms`, drops entries that don't parse instead of failing the whole report
+pub fn deserialize_entries<'de, D>(deserializer: D) -> Result<Option<Vec<HmsEntry>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(raw) = Option::<Vec<serde_json::Value>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let entries = raw
+        .into_iter()
+        .filter_map(|v| match serde_json::from_value::<HmsEntry>(v.clone()) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                trace!("skipping hms entry {}: {:?}", v, e);
+                None
+            }
+        })
+        .collect();
+
+    Ok(Some(entries))
+}
+
+/// MARK: HmsMessage
+/// an entry with its description from the `ErrorMap`, filled in by the manager
+#[derive(Debug, Clone, PartialEq)]
+pub struct HmsMessage {
+    pub entry: HmsEntry,
+    pub text: Option<String>,
+}
+
+impl HmsMessage {
+    pub fn new(entry: HmsEntry) -> Self {
+        Self { entry, text: None }
+    }
+
+    pub fn text(&self) -> &str {
+        self.text.as_deref().unwrap_or("Unknown HMS message")
+    }
+}
+
+/// ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HmsSeverity {
+    Unknown,
+    Info,
+    Common,
+    Serious,
+    Fatal,
+}
+
+impl HmsSeverity {
+    pub fn to_text(&self) -> &'static str {
+        match self {
+            HmsSeverity::Unknown => "Unknown",
+            HmsSeverity::Info => "Info",
+            HmsSeverity::Common => "Common",
+            HmsSeverity::Serious => "Serious",
+            HmsSeverity::Fatal => "Fatal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HmsModule {
+    MotionController,
+    Mainboard,
+    Ams,
+    Toolhead,
+    Xcam,
+    Unknown(u8),
+}
+
+impl std::fmt::Display for HmsModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HmsModule::MotionController => write!(f, "Motion Controller"),
+            HmsModule::Mainboard => write!(f, "Mainboard"),
+            HmsModule::Ams => write!(f, "AMS"),
+            HmsModule::Toolhead => write!(f, "Toolhead"),
+            HmsModule::Xcam => write!(f, "XCam"),
+            HmsModule::Unknown(m) => write!(f, "Module {:02X}", m),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::conn_manager::conn_bambu::message::Print;
+
+    fn parse_hms(hms: serde_json::Value) -> Option<Vec<HmsEntry>> {
+        let print: Print = serde_json::from_value(json!({ "print": { "hms": hms } })).unwrap();
+        print.print.hms
+    }
+
+    #[test]
+    fn bad_entries_are_skipped() {
+        let hms = parse_hms(json!([
+            { "attr": 0x0300_1200, "code": 0x0002_0001 },
+            { "attr": -1, "code": 5 },
+            { "code": 7 },
+            "garbage",
+            { "attr": 0x0700_0000, "code": 0x0001_0002 },
+        ]));
+
+        assert_eq!(
+            hms,
+            Some(vec![
+                HmsEntry {
+                    attr: 0x0300_1200,
+                    code: 0x0002_0001
+                },
+                HmsEntry {
+                    attr: 0x0700_0000,
+                    code: 0x0001_0002
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn missing_or_null_hms() {
+        let print: Print = serde_json::from_value(json!({ "print": {} })).unwrap();
+        assert_eq!(print.print.hms, None);
+        assert_eq!(parse_hms(json!(null)), None);
+        assert_eq!(parse_hms(json!([])), Some(vec![]));
+    }
+
+    #[test]
+    fn code_str_and_module() {
+        let entry = HmsEntry {
+            attr: 0x0700_1200,
+            code: 0x0002_0001,
+        };
+        assert_eq!(entry.code_str(), "0700_1200_0002_0001");
+        assert_eq!(entry.severity(), HmsSeverity::Serious);
+        assert_eq!(entry.ams_id(), Some(0x00));
+    }
+}
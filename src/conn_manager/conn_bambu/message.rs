@@ -75,7 +75,8 @@ pub struct PrintData {
     pub total_layer_num: Option<i64>,
     pub s_obj: Option<Vec<Value>>,
     pub fan_gear: Option<i64>,
-    pub hms: Option<Vec<Value>>,
+    #[serde(default, deserialize_with = "super::hms::deserialize_entries")]
+    pub hms: Option<Vec<super::hms::HmsEntry>>,
     pub online: Option<PrintOnline>,
     pub ams: Option<PrintAms>,
     pub ipcam: Option<PrintIpcam>,
@@ -87,6 +88,9 @@ pub struct PrintData {
     pub command: Option<String>,
     pub msg: Option<i64>,
     pub sequence_id: Option<String>,
+    /// only present in replies to commands
+    pub result: Option<String>,
+    pub reason: Option<String>,
     pub device: Option<crate::status::bambu_status::Device>,
 }
 
@@ -126,6 +130,8 @@ pub struct PrintAms {
 pub struct PrintAmsData {
     pub id: String,
     pub humidity: String,
+    /// percent, only sent by newer firmware
+    pub humidity_raw: Option<String>,
     pub temp: String,
     pub info: Option<String>,
     pub tray: Vec<PrintTray>,
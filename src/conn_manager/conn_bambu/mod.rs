@@ -1,9 +1,12 @@
 pub mod bambu_listener;
 pub mod bambu_proto;
-mod command;
+pub mod command;
 pub mod errors;
+pub mod ftps;
+pub mod hms;
 pub mod message;
 mod parse;
+pub mod thumbnail;
 // pub mod streaming;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tokio::sync::OnceCell;
+
+use super::ftps::BambuFtps;
+use crate::{
+    config::{printer_config::PrinterConfigBambu, AppConfig},
+    conn_manager::files,
+    status::bambu_status::PrinterStateBambu,
+};
+
+/// plate preview for the current print.
+/// from the cloud task if logged in, otherwise from the .3mf on the printer
+pub async fn fetch(
+    config: &AppConfig,
+    printer: &PrinterConfigBambu,
+    state: &PrinterStateBambu,
+) -> Result<Vec<u8>> {
+    if config.logged_in() {
+        match fetch_cloud(config, printer, state).await {
+            Ok(img) => return Ok(img),
+            Err(e) => warn!("cloud thumbnail failed, trying LAN: {:?}", e),
+        }
+    }
+    fetch_lan(printer, state).await
+}
+
+/// MARK: cloud
+async fn fetch_cloud(
+    config: &AppConfig,
+    printer: &PrinterConfigBambu,
+    state: &PrinterStateBambu,
+) -> Result<Vec<u8>> {
+    let client = reqwest::Client::new();
+
+    let url = match state.current_task_thumbnail_url.clone() {
+        Some(url) => url,
+        None => {
+            let token = config
+                .auth()
+                .write()
+                .await
+                .get_token()?
+                .context("not logged in")?;
+            task_cover_url(&client, token.get_token(), &printer.serial, state).await?
+        }
+    };
+
+    debug!("fetching cloud thumbnail: {:?}", url);
+    let bytes = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+/// cover image of the printer's most recent matching task
+async fn task_cover_url(
+    client: &reqwest::Client,
+    token: &str,
+    serial: &str,
+    state: &PrinterStateBambu,
+) -> Result<String> {
+    const URL: &str = "https://api.bambulab.com/v1/user-service/my/tasks";
+
+    let resp: serde_json::Value = client
+        .get(URL)
+        .bearer_auth(token)
+        .query(&[("deviceId", serial), ("limit", "5")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let hits = resp
+        .get("hits")
+        .and_then(|h| h.as_array())
+        .context("no tasks")?;
+
+    /// another task's cover would show the wrong print, so this falls back to LAN instead
+    let task_id = state.task_id.as_deref().unwrap_or_default();
+    ensure!(!task_id.is_empty(), "no task id");
+    let task = hits
+        .iter()
+        .find(|t| {
+            t.get("id")
+                .map(|id| id.to_string().trim_matches('"') == task_id)
+                == Some(true)
+        })
+        .with_context(|| format!("no task matching {:?}", task_id))?;
+
+    task.get("cover")
+        .and_then(|c| c.as_str())
+        .map(|c| c.to_string())
+        .context("task has no cover image")
+}
+
+/// MARK: LAN
+/// the last preview per printer serial, keyed by `thumbnail_key`.
+/// concurrent requests for the same print wait on one download
+static LAN_CACHE: LazyLock<Mutex<HashMap<String, (String, PreviewCell)>>> =
+    LazyLock::new(Default::default);
+
+type PreviewCell = Arc<OnceCell<Vec<u8>>>;
+
+async fn fetch_lan(printer: &PrinterConfigBambu, state: &PrinterStateBambu) -> Result<Vec<u8>> {
+    let key = state.thumbnail_key().context("no current print")?;
+
+    let cell = {
+        let mut cache = LAN_CACHE.lock().unwrap();
+        match cache.get(&printer.serial) {
+            Some((k, cell)) if *k == key => cell.clone(),
+            _ => {
+                let cell = Arc::new(OnceCell::new());
+                cache.insert(printer.serial.clone(), (key, cell.clone()));
+                cell
+            }
+        }
+    };
+
+    /// a failed download leaves the cell empty, so the next request tries again
+    cell.get_or_try_init(|| download_lan(printer, state))
+        .await
+        .cloned()
+}
+
+/// downloads the .3mf being printed over FTPS and extracts the plate preview
+async fn download_lan(printer: &PrinterConfigBambu, state: &PrinterStateBambu) -> Result<Vec<u8>> {
+    let mut ftps =
+        BambuFtps::connect(&printer.host, files::FTPS_PORT, &printer.access_code).await?;
+
+    let path = find_project_file(&mut ftps, state).await?;
+    let data = ftps.download(&path).await?;
+    if let Err(e) = ftps.quit().await {
+        debug!("ftps quit: {:?}", e);
+    }
+
+    let plate = plate_number(state.current_file.as_deref().unwrap_or_default());
+    debug!("extracting plate {} preview from {:?}", plate, path);
+    plate_preview(data, plate)
+}
+
+/// the printer keeps files sent from the slicer in /cache, and ones from the SD card at the root
+async fn find_project_file(ftps: &mut BambuFtps, state: &PrinterStateBambu) -> Result<String> {
+    let file = state.current_file.as_deref().unwrap_or_default();
+    let name = state.subtask_name.as_deref().unwrap_or_default();
+
+    let mut wanted = vec![];
+    if file.ends_with(".3mf") {
+        wanted.push(file.rsplit('/').next().unwrap_or(file).to_string());
+    }
+    if !name.is_empty() {
+        wanted.push(format!("{}.gcode.3mf", name));
+        wanted.push(format!("{}.3mf", name));
+    }
+    ensure!(!wanted.is_empty(), "no project file for {:?}", file);
+
+    for dir in ["/cache", "/"] {
+        let names = match ftps.list(dir).await {
+            Ok(names) => names,
+            Err(e) => {
+                debug!("ftps list {:?}: {:?}", dir, e);
+                continue;
+            }
+        };
+        if let Some(f) = wanted.iter().find(|w| names.contains(w)) {
+            return Ok(format!("{}/{}", dir.trim_end_matches('/'), f));
+        }
+    }
+    bail!("project file not found on printer: {:?}", wanted)
+}
+
+/// from e.g. "/data/Metadata/plate_2.gcode", 1 if there's no plate in the name
+fn plate_number(gcode_file: &str) -> u32 {
+    gcode_file
+        .rsplit_once("plate_")
+        .and_then(|(_, rest)| {
+            rest.split(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse()
+                .ok()
+        })
+        .unwrap_or(1)
+}
+
+/// MARK: zip
+/// `Metadata/plate_N.png` from a .3mf
+fn plate_preview(data: Vec<u8>, plate: u32) -> Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+    let mut entry = archive
+        .by_name(&format!("Metadata/plate_{}.png", plate))
+        .with_context(|| format!("no preview for plate {}", plate))?;
+
+    let mut out = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut out)?;
+    Ok(out)
+}
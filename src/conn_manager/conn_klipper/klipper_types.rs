@@ -71,32 +71,35 @@ pub mod metadata {
     use serde::Deserialize;
     use serde::Serialize;
 
+    /// `server.files.metadata`, most fields depend on the slicer
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct KlipperMetadata {
-        pub chamber_temp: f64,
-        pub estimated_time: i64,
-        pub filament_name: String,
+        pub chamber_temp: Option<f64>,
+        /// seconds
+        pub estimated_time: Option<f64>,
+        pub filament_name: Option<String>,
         pub filament_total: Option<f64>,
-        pub filament_type: String,
-        pub filament_weight_total: f64,
+        pub filament_type: Option<String>,
+        pub filament_weight_total: Option<f64>,
         pub filename: String,
-        pub first_layer_bed_temp: f64,
-        pub first_layer_extr_temp: f64,
-        pub first_layer_height: f64,
-        pub gcode_end_byte: i64,
-        pub gcode_start_byte: i64,
-        pub job_id: String,
-        pub layer_count: i64,
-        pub layer_height: f64,
+        pub first_layer_bed_temp: Option<f64>,
+        pub first_layer_extr_temp: Option<f64>,
+        pub first_layer_height: Option<f64>,
+        pub gcode_end_byte: Option<i64>,
+        pub gcode_start_byte: Option<i64>,
+        pub job_id: Option<String>,
+        pub layer_count: Option<i64>,
+        pub layer_height: Option<f64>,
         pub modified: f64,
-        pub nozzle_diameter: f64,
-        pub object_height: f64,
-        pub print_start_time: f64,
+        pub nozzle_diameter: Option<f64>,
+        pub object_height: Option<f64>,
+        pub print_start_time: Option<f64>,
         pub size: i64,
-        pub slicer: String,
-        pub slicer_version: String,
+        pub slicer: Option<String>,
+        pub slicer_version: Option<String>,
         pub thumbnails: Vec<Thumbnail>,
-        pub uuid: String,
+        pub uuid: Option<String>,
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1,4 +1,4 @@
-mod klipper_types;
+pub mod klipper_types;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use tracing::{debug, error, info, trace, warn};
@@ -8,11 +8,14 @@ use futures::{
     SinkExt, StreamExt,
 };
 use serde_json::Value;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Instant};
 use tokio::{net::TcpStream, sync::RwLock};
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 
-use super::{worker_message::WorkerMsg, WorkerCmd};
+use super::{
+    toolchanger::ToolchangerCmd, worker_message::WorkerMsg, CommandId, CommandResult, WorkerCmd,
+    COMMAND_TIMEOUT,
+};
 use crate::{
     config::{printer_config::PrinterConfigKlipper, printer_id::PrinterId},
     status::PrinterStateUpdate,
@@ -34,14 +37,117 @@ pub struct KlipperClient {
     msg_id: usize,
 
     tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
     kill_rx: tokio::sync::oneshot::Receiver<()>,
     update_timer: tokio::time::Interval,
 
+    /// JSON-RPC id -> command waiting for a response
+    pending_cmds: HashMap<usize, CommandId>,
+    /// JSON-RPC id -> file of a `server.files.metadata` request, and when it was sent
+    pending_metadata: HashMap<usize, (String, Instant)>,
+
     extruders: Vec<String>,
     fans: Vec<String>,
 
     current_print: Option<(String, KlipperMetadata)>,
+    progress: PrintProgress,
+}
+
+/// subscription updates only contain what changed, so keep what's needed for the ETA
+#[derive(Debug, Default)]
+struct PrintProgress {
+    state: String,
+    filename: String,
+    /// seconds, excluding pauses
+    print_duration: f64,
+    /// from `virtual_sdcard`, 0.0 - 1.0
+    progress: f64,
+    file_position: i64,
+    /// from `SET_PRINT_STATS_INFO`, if the slicer or macros use it
+    current_layer: Option<i64>,
+    total_layer: Option<i64>,
+    z: Option<f64>,
+}
+
+impl PrintProgress {
+    fn update(&mut self, status: &serde_json::Map<String, Value>) {
+        if let Some(s) = status.get("print_stats") {
+            if let Some(state) = s.get("state").and_then(|s| s.as_str()) {
+                self.state = state.to_string();
+            }
+            if let Some(f) = s.get("filename").and_then(|f| f.as_str()) {
+                self.filename = f.to_string();
+            }
+            if let Some(t) = s.get("print_duration").and_then(|t| t.as_f64()) {
+                self.print_duration = t;
+            }
+            if let Some(info) = s.get("info") {
+                if let Some(l) = info.get("current_layer") {
+                    self.current_layer = l.as_i64();
+                }
+                if let Some(l) = info.get("total_layer") {
+                    self.total_layer = l.as_i64();
+                }
+            }
+        }
+        if let Some(s) = status.get("virtual_sdcard") {
+            if let Some(p) = s.get("progress").and_then(|p| p.as_f64()) {
+                self.progress = p;
+            }
+            if let Some(p) = s.get("file_position").and_then(|p| p.as_i64()) {
+                self.file_position = p;
+            }
+        }
+        if let Some(z) = status
+            .get("gcode_move")
+            .and_then(|g| g.pointer("/gcode_position/2"))
+            .and_then(|z| z.as_f64())
+        {
+            self.z = Some(z);
+        }
+    }
+
+    /// progress through the G-code itself, skipping the thumbnails and header
+    fn file_progress(&self, md: &KlipperMetadata) -> Option<f64> {
+        let (start, end) = (md.gcode_start_byte?, md.gcode_end_byte?);
+        if end <= start {
+            return None;
+        }
+        let p = (self.file_position - start) as f64 / (end - start) as f64;
+        Some(p.clamp(0., 1.))
+    }
+
+    /// seconds left, from the slicer's estimate if there is one
+    fn remaining(&self, md: Option<&KlipperMetadata>) -> Option<f64> {
+        if let Some(est) = md.and_then(|md| md.estimated_time).filter(|t| *t > 0.) {
+            return Some((est - self.print_duration).max(0.));
+        }
+
+        let progress = md
+            .and_then(|md| self.file_progress(md))
+            .unwrap_or(self.progress);
+        if progress <= 0. || self.print_duration <= 0. {
+            return None;
+        }
+        Some(self.print_duration / progress - self.print_duration)
+    }
+
+    /// (current, total), estimated from the height if the printer doesn't report it
+    fn layers(&self, md: Option<&KlipperMetadata>) -> Option<(u32, u32)> {
+        let total = self.total_layer.or(md.and_then(|md| md.layer_count))?;
+        let current = match self.current_layer {
+            Some(l) => l,
+            None => {
+                let md = md?;
+                let (first, height) = (md.first_layer_height?, md.layer_height?);
+                if height <= 0. {
+                    return None;
+                }
+                ((self.z? - first) / height).ceil() as i64 + 1
+            }
+        };
+        Some((current.clamp(0, total) as u32, total as u32))
+    }
 }
 
 /// new, run
@@ -50,7 +156,7 @@ impl KlipperClient {
         id: PrinterId,
         printer_cfg: Arc<RwLock<PrinterConfigKlipper>>,
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
         kill_rx: tokio::sync::oneshot::Receiver<()>,
     ) -> Result<Self> {
         let url = printer_cfg.read().await.host.clone();
@@ -83,10 +189,14 @@ impl KlipperClient {
             kill_rx,
             update_timer: tokio::time::interval(tokio::time::Duration::from_secs(1)),
 
+            pending_cmds: HashMap::new(),
+            pending_metadata: HashMap::new(),
+
             extruders,
             fans,
 
             current_print: None,
+            progress: PrintProgress::default(),
         };
 
         out.init().await?;
@@ -105,7 +215,6 @@ impl KlipperClient {
                 // "absolute_coordinates",
                 ],
             // "gcode_move": null,
-            // "toolhead": ["position", "homed_axes"],
             "toolhead": ["homed_axes"],
             // "toolhead": null,
             // "motion_report": null,
@@ -114,6 +223,7 @@ impl KlipperClient {
             "heater_bed": ["temperature", "target", "power"],
             "print_stats": ["state", "filename", "total_duration", "print_duration", "message", "info"],
             "display_status": ["progress"],
+            "virtual_sdcard": ["progress", "file_position"],
             "save_variables": null,
             "fan": null,
         });
@@ -161,9 +271,23 @@ impl KlipperClient {
                 //     // debug!("got update: {:#?}", update);
                 //     // self.tx.send((self.id.clone(), WorkerMsg::StatusUpdate(update))).unwrap();
                 // }
-                cmd = self.cmd_rx.recv() => {
-                    debug!("got worker command");
-                    //
+                Some((cmd_id, cmd)) = self.cmd_rx.recv() => {
+                    debug!("got worker command: {:?}", cmd);
+                    match self.handle_command(cmd).await {
+                        Ok(rpc_id) => {
+                            self.pending_cmds.insert(rpc_id, cmd_id);
+                        }
+                        Err(e) => {
+                            error!("error handling command: {:?}", e);
+                            self.tx.send((
+                                self.id.clone(),
+                                WorkerMsg::CommandResult(
+                                    cmd_id,
+                                    CommandResult::Rejected(e.to_string()),
+                                ),
+                            ))?;
+                        }
+                    }
                 }
                 _ = &mut self.kill_rx => {
                     debug!("got kill command");
@@ -200,6 +324,10 @@ impl KlipperClient {
                 // );
                 // self.tx.send((self.id.clone(), WorkerMsg::StatusUpdate(update))).unwrap();
 
+                if self.handle_response(&json)? {
+                    return Ok(());
+                }
+
                 self.handle_status(json).await?;
             }
             _ => {}
@@ -207,6 +335,35 @@ impl KlipperClient {
         Ok(())
     }
 
+    /// returns true if the message was a response to a command
+    fn handle_response(&mut self, msg: &Value) -> Result<bool> {
+        let Some(rpc_id) = msg.get("id").and_then(|id| id.as_u64()) else {
+            return Ok(false);
+        };
+        if let Some((filename, _)) = self.pending_metadata.remove(&(rpc_id as usize)) {
+            self.handle_metadata(filename, msg);
+            return Ok(true);
+        }
+        let Some(cmd_id) = self.pending_cmds.remove(&(rpc_id as usize)) else {
+            return Ok(false);
+        };
+
+        let result = if let Some(e) = msg.get("error") {
+            let e = e
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(|m| m.to_string())
+                .unwrap_or_else(|| e.to_string());
+            CommandResult::Rejected(e)
+        } else {
+            CommandResult::Acked
+        };
+
+        self.tx
+            .send((self.id.clone(), WorkerMsg::CommandResult(cmd_id, result)))?;
+        Ok(true)
+    }
+
     async fn handle_status(&mut self, msg: Value) -> Result<()> {
         let status = {
             if let Some(status) = msg.pointer("/params/0").and_then(|s| s.as_object()) {
@@ -298,6 +455,31 @@ impl KlipperClient {
             }
         }
 
+        /// toolhead position, as seen by G-code (including offsets)
+        if let Some(pos) = status
+            .get("gcode_move")
+            .and_then(|g| g.get("gcode_position"))
+            .and_then(|p| p.as_array())
+        {
+            let pos = pos
+                .iter()
+                .take(3)
+                .filter_map(|v| v.as_f64().map(|v| v as f32))
+                .collect::<Vec<_>>();
+            if let [x, y, z] = pos[..] {
+                updates.push(PrinterStateUpdate::Position([x, y, z]));
+            }
+        }
+        if let Some(axes) = status
+            .get("toolhead")
+            .and_then(|t| t.get("homed_axes"))
+            .and_then(|a| a.as_str())
+        {
+            updates.push(PrinterStateUpdate::HomedAxes(
+                crate::status::HomedAxes::from_klipper(axes),
+            ));
+        }
+
         /// save variables to get current tool
         if let Some(vars) = status.get("save_variables") {
             if let Some(vars) = vars.get("variables") {
@@ -314,6 +496,10 @@ impl KlipperClient {
             }
         }
 
+        /// ETA, duration, layers
+        self.progress.update(status);
+        updates.extend(self.print_progress().await?);
+
         self.tx.send((
             self.id.clone(),
             WorkerMsg::StatusUpdate(crate::status::GenericPrinterStateUpdate(updates)),
@@ -321,6 +507,195 @@ impl KlipperClient {
 
         Ok(())
     }
+
+    async fn print_progress(&mut self) -> Result<Vec<PrinterStateUpdate>> {
+        let mut out = vec![];
+
+        if self.progress.filename.is_empty() {
+            if self.current_print.take().is_some() {
+                out.push(PrinterStateUpdate::TimeRemaining(chrono::Duration::zero()));
+            }
+            return Ok(out);
+        }
+
+        /// requests Moonraker never answered are dropped so they get sent again
+        self.pending_metadata
+            .retain(|_, (_, sent)| sent.elapsed() < COMMAND_TIMEOUT);
+
+        let filename = self.progress.filename.clone();
+        if self.current_print.as_ref().map(|(f, _)| f) != Some(&filename)
+            && !self.pending_metadata.values().any(|(f, _)| f == &filename)
+        {
+            self.request_metadata(&filename).await?;
+        }
+
+        if !matches!(self.progress.state.as_str(), "printing" | "paused") {
+            return Ok(out);
+        }
+
+        let md = self
+            .current_print
+            .as_ref()
+            .filter(|(f, _)| f == &filename)
+            .map(|(_, md)| md);
+
+        out.push(PrinterStateUpdate::Duration(chrono::Duration::seconds(
+            self.progress.print_duration as i64,
+        )));
+        if let Some(rem) = self.progress.remaining(md) {
+            out.push(PrinterStateUpdate::TimeRemaining(
+                chrono::Duration::seconds(rem as i64),
+            ));
+        }
+        if let Some((current, total)) = self.progress.layers(md) {
+            out.push(PrinterStateUpdate::ProgressLayers(current, total));
+        }
+
+        Ok(out)
+    }
+
+    fn handle_metadata(&mut self, filename: String, msg: &Value) {
+        let md = match msg
+            .get("result")
+            .map(|r| serde_json::from_value::<KlipperMetadata>(r.clone()))
+        {
+            Some(Ok(md)) => md,
+            Some(Err(e)) => {
+                warn!("failed to parse metadata for {:?}: {:?}", filename, e);
+                KlipperMetadata::default()
+            }
+            /// e.g. file not found, fall back to file position for the ETA
+            None => {
+                warn!("no metadata for {:?}: {:?}", filename, msg.get("error"));
+                KlipperMetadata::default()
+            }
+        };
+        debug!(
+            "metadata for {:?}: estimated_time = {:?}, layer_count = {:?}",
+            filename, md.estimated_time, md.layer_count
+        );
+        self.current_print = Some((filename, md));
+    }
+}
+
+/// commands
+impl KlipperClient {
+    /// returns the JSON-RPC id to match the response with
+    async fn handle_command(&mut self, cmd: WorkerCmd) -> Result<usize> {
+        let rpc_id = match cmd {
+            WorkerCmd::Pause => self.send_rpc("printer.print.pause", None).await?,
+            WorkerCmd::Resume => self.send_rpc("printer.print.resume", None).await?,
+            WorkerCmd::Cancel => self.send_rpc("printer.print.cancel", None).await?,
+            WorkerCmd::SetNozzleTemp(tool, temp) => {
+                let Some(heater) = self.extruders.get(tool.unwrap_or(0)) else {
+                    bail!("unknown tool: {:?}", tool);
+                };
+                let script = format!(
+                    "SET_HEATER_TEMPERATURE HEATER={} TARGET={:.0}",
+                    heater, temp
+                );
+                self.send_gcode(&script).await?
+            }
+            WorkerCmd::SetBedTemp(temp) => {
+                let script = format!(
+                    "SET_HEATER_TEMPERATURE HEATER=heater_bed TARGET={:.0}",
+                    temp
+                );
+                self.send_gcode(&script).await?
+            }
+            WorkerCmd::SetFan(speed) => {
+                let speed = (speed.clamp(0., 1.) * 255.).round() as u8;
+                self.send_gcode(&format!("M106 S{}", speed)).await?
+            }
+            WorkerCmd::SetLight(_) => bail!("light control not supported on klipper"),
+            WorkerCmd::SetAmsFilament(_) => bail!("AMS not supported on klipper"),
+            WorkerCmd::PrintFile(path) => {
+                /// relative to the gcodes root
+                let params = serde_json::json!({ "filename": path.trim_start_matches('/') });
+                self.send_rpc("printer.print.start", Some(params)).await?
+            }
+            WorkerCmd::PrintProjectFile(_) => bail!("project files not supported on klipper"),
+            WorkerCmd::Gcode(gcode) => self.send_gcode(&gcode).await?,
+            WorkerCmd::EmergencyStop => self.send_rpc("printer.emergency_stop", None).await?,
+            WorkerCmd::FirmwareRestart => self.send_rpc("printer.firmware_restart", None).await?,
+            WorkerCmd::Toolchanger(cmd) => {
+                let (toolchanger, tools) = {
+                    let cfg = self.printer_cfg.read().await;
+                    (cfg.toolchanger, cfg.tools)
+                };
+                ensure!(
+                    toolchanger,
+                    "not a toolchanger, set `toolchanger = true` in the config"
+                );
+                if let Some(tool) = cmd.tool() {
+                    ensure!(
+                        tool < tools,
+                        "unknown tool: {}, the printer has {}",
+                        tool,
+                        tools
+                    );
+                }
+                self.send_gcode(&Self::toolchanger_script(&cmd)).await?
+            }
+            WorkerCmd::Motion(cmd) => self.send_gcode(&cmd.to_gcode().join("\n")).await?,
+        };
+        Ok(rpc_id)
+    }
+
+    /// KTCC macros, `T<n>` picks up a tool
+    fn toolchanger_script(cmd: &ToolchangerCmd) -> String {
+        match cmd {
+            ToolchangerCmd::PickupTool { tool } => format!("T{}", tool),
+            ToolchangerCmd::ParkTool => "KTCC_TOOL_DROPOFF_ALL".to_string(),
+            ToolchangerCmd::LoadFilament {
+                tool,
+                filament,
+                temp,
+            } => {
+                let mut lines = vec![];
+                if let Some(temp) = temp {
+                    lines.push(format!("M109 T{} S{:.0}", tool, temp));
+                }
+                lines.push(format!("T{}", tool));
+                /// macros that don't take a material ignore the extra parameter
+                lines.push(format!("LOAD_FILAMENT MATERIAL={}", filament));
+                lines.join("\n")
+            }
+            ToolchangerCmd::UnloadFilament { tool } => format!("T{}\nUNLOAD_FILAMENT", tool),
+            ToolchangerCmd::SetOffset { tool, x, y, z } => format!(
+                "KTCC_SET_TOOL_OFFSET TOOL={} X={:.3} Y={:.3} Z={:.3}",
+                tool, x, y, z
+            ),
+        }
+    }
+
+    async fn send_gcode(&mut self, script: &str) -> Result<usize> {
+        self.send_rpc(
+            "printer.gcode.script",
+            Some(serde_json::json!({ "script": script })),
+        )
+        .await
+    }
+
+    /// returns the JSON-RPC id of the request
+    async fn send_rpc(&mut self, method: &str, params: Option<Value>) -> Result<usize> {
+        let id = self.get_id();
+        let mut msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "id": id,
+        });
+        if let Some(params) = params {
+            msg["params"] = params;
+        }
+
+        self.ws_write
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                msg.to_string().into(),
+            ))
+            .await?;
+        Ok(id)
+    }
 }
 
 /// helpers
@@ -344,6 +719,18 @@ impl KlipperClient {
         Ok(())
     }
 
+    async fn request_metadata(&mut self, filename: &str) -> Result<()> {
+        let id = self
+            .send_rpc(
+                "server.files.metadata",
+                Some(serde_json::json!({ "filename": filename })),
+            )
+            .await?;
+        self.pending_metadata
+            .insert(id, (filename.to_string(), Instant::now()));
+        Ok(())
+    }
+
     async fn get_variables(&mut self) -> Result<()> {
         let msg = serde_json::json!({
                 "jsonrpc": "2.0",
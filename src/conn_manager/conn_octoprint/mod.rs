@@ -1,5 +1,7 @@
 pub mod octo_commands;
+pub mod octo_push;
 pub mod octo_types;
+pub mod octo_worker;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use octo_commands::OctoCmd;
@@ -60,20 +62,30 @@ impl OctoClientLocal {
 
 /// get_response, get_update
 impl OctoClientLocal {
-    pub async fn get_response<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
-        // let printer = self.printer_cfg.read().await;
+    /// `path` relative to the server root, each segment percent-encoded
+    fn url(&self, path: &str) -> Result<reqwest::Url> {
         let printer = &self.printer_cfg;
-        let token = printer.token.clone();
-        let host = printer.host.clone();
-
-        let url = format!("http://{}:5000/{}", host, url);
+        let mut url = reqwest::Url::parse(&format!("http://{}:{}/", printer.host, printer.port))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("bad host: {}", printer.host))?
+            .pop_if_empty()
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        Ok(url)
+    }
 
-        let res = self
+    async fn get(&self, url: reqwest::Url) -> Result<reqwest::Response> {
+        let token = self.printer_cfg.token.clone();
+        Ok(self
             .client
-            .get(&url)
+            .get(url)
             .header("X-Api-Key", token)
             .send()
-            .await?;
+            .await?)
+    }
+
+    pub async fn get_response<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let url = self.url(url)?;
+        let res = self.get(url.clone()).await?;
 
         if !res.status().is_success() {
             warn!("status {:#?}", res.status());
@@ -83,84 +95,147 @@ impl OctoClientLocal {
         Ok(res.json().await?)
     }
 
+    /// state, temperatures and the current job, for a standalone OctoPrint printer
     pub async fn get_update(&self) -> Result<GenericPrinterStateUpdate> {
-        let mut out = vec![];
+        let Some(printer_state) = self.get_printer_state().await? else {
+            return Ok(GenericPrinterStateUpdate(vec![PrinterStateUpdate::State(
+                PrinterState::Disconnected,
+            )]));
+        };
+        let job = self.get_job().await?;
+
+        Ok(GenericPrinterStateUpdate(Self::status_updates(
+            &printer_state.state,
+            Some(&printer_state.temperature),
+            &job.job,
+            &job.progress,
+        )))
+    }
 
-        // let job_info = self.get_job_info().await?;
+    /// from a poll or a push `current` message
+    pub(super) fn status_updates(
+        state: &octo_types::printer_status::State,
+        temperature: Option<&octo_types::printer_status::Temperature>,
+        job: &octo_types::job::Job,
+        progress: &octo_types::job::Progress,
+    ) -> Vec<PrinterStateUpdate> {
+        let state = Self::state_from_flags(state);
+        let mut out = vec![PrinterStateUpdate::State(state.clone())];
+
+        if let Some(temperature) = temperature {
+            out.extend(Self::temperature_updates(temperature));
+            /// the first tool is also the nozzle, for single extruder printers
+            if let Some((_, tool)) = temperature.tools.first() {
+                out.push(PrinterStateUpdate::NozzleTemp(None, tool.actual));
+                out.push(PrinterStateUpdate::NozzleTempTarget(
+                    None,
+                    tool.target.unwrap_or(0.),
+                ));
+            }
+        }
+
+        /// the path is what thumbnails are fetched with
+        if let Some(path) = job.file.path.as_ref().or(job.file.name.as_ref()) {
+            out.push(PrinterStateUpdate::CurrentFile(path.clone()));
+        }
+        if let Some(completion) = progress.completion {
+            out.push(PrinterStateUpdate::Progress(completion));
+        }
+        if matches!(state, PrinterState::Printing | PrinterState::Paused) {
+            if let Some(t) = progress.print_time {
+                out.push(PrinterStateUpdate::Duration(chrono::Duration::seconds(t)));
+            }
+            if let Some(t) = progress.print_time_left {
+                out.push(PrinterStateUpdate::TimeRemaining(
+                    chrono::Duration::seconds(t),
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// temperatures only, for OctoPrint running alongside PrusaLink
+    pub async fn get_temperatures(&self) -> Result<GenericPrinterStateUpdate> {
+        let Some(printer_state) = self.get_printer_state().await? else {
+            return Ok(GenericPrinterStateUpdate(vec![]));
+        };
+        Ok(GenericPrinterStateUpdate(Self::temperature_updates(
+            &printer_state.temperature,
+        )))
+    }
 
-        let printer_state = self.get_printer_state().await?;
+    fn temperature_updates(
+        temperature: &octo_types::printer_status::Temperature,
+    ) -> Vec<PrinterStateUpdate> {
+        let mut out = vec![];
 
         out.push(PrinterStateUpdate::BedTemp(
-            printer_state.temperature.bed.actual,
-            // printer_state.temperature.bed.target,
+            temperature.bed.actual,
+            // temperature.bed.target,
         ));
-        if let Some(t) = printer_state.temperature.bed.target {
+        if let Some(t) = temperature.bed.target {
             out.push(PrinterStateUpdate::BedTempTarget(t));
         }
 
-        for (id, tool) in printer_state.temperature.tools {
+        for (id, tool) in temperature.tools.iter() {
             // out.push(PrinterStateUpdate::NozzleTemp(Some(id), tool.actual, tool.target));
-            out.push(PrinterStateUpdate::NozzleTemp(
-                Some(id),
-                tool.actual,
-            ));
+            out.push(PrinterStateUpdate::NozzleTemp(Some(*id), tool.actual));
             if let Some(t) = tool.target {
-                out.push(PrinterStateUpdate::NozzleTempTarget(Some(id), t));
+                out.push(PrinterStateUpdate::NozzleTempTarget(Some(*id), t));
             }
         }
 
-        // let flags = printer_state.state.flags;
-        // let state = if flags.printing {
-        //     PrinterState::Printing
-        // } else if flags.paused {
-        //     PrinterState::Paused
-        // } else if flags.error {
-        //     PrinterState::Error
-        // } else {
-        //     PrinterState::Idle
-        // };
-        // out.push(PrinterStateUpdate::State(state));
-
-        Ok(GenericPrinterStateUpdate(out))
-
-        // Ok(GenericPrinterStateUpdate {
-        //     // state: Some(state),
-        //     // nozzle_temp: Some(status.printer.temp_nozzle as f32),
-        //     // bed_temp: Some(status.printer.temp_bed as f32),
-        //     // nozzle_temp_target: Some(status.printer.target_nozzle as f32),
-        //     // bed_temp_target: Some(status.printer.target_bed as f32),
-        //     // progress: Some(status.job.progress as f32),
-        //     // time_printing,
-        //     // time_remaining,
-        //     // current_file: Some(job.file.display_name),
-        //     ..Default::default()
-        // })
+        out
+    }
+
+    fn state_from_flags(state: &octo_types::printer_status::State) -> PrinterState {
+        let flags = &state.flags;
+        if flags.pausing || flags.cancelling {
+            PrinterState::Busy
+        } else if flags.paused {
+            PrinterState::Paused
+        } else if flags.printing {
+            PrinterState::Printing
+        } else if flags.error {
+            PrinterState::Error(Some(state.text.clone()))
+        } else if flags.closed_or_error {
+            PrinterState::Disconnected
+        } else if flags.operational || flags.ready {
+            PrinterState::Idle
+        } else {
+            PrinterState::Unknown(state.text.clone())
+        }
     }
 }
 
 /// send commands
 impl OctoClientLocal {
     // pub async fn send_command<T: DeserializeOwned>(&self, cmd: &OctoCmd) -> Result<T>
-    pub async fn send_command(&self, cmd: &OctoCmd) -> Result<reqwest::Response> {
+    /// errors include the response body, since OctoPrint explains rejections there
+    pub async fn send_command(&self, cmd: &OctoCmd) -> Result<()> {
         // let printer = self.printer_cfg.read().await;
         let printer = &self.printer_cfg;
         let token = printer.token.clone();
-        let host = printer.host.clone();
 
         let url = match cmd {
             // OctoCmd::ParkTool => todo!(),
             OctoCmd::Jog { .. } | OctoCmd::Home { .. } | OctoCmd::SetFeedrate(..) => {
-                "api/printer/printhead"
+                "api/printer/printhead".to_string()
             }
             OctoCmd::PickupTool(_) 
             | OctoCmd::ParkTool 
             | OctoCmd::ChangeFilament(_) 
+            | OctoCmd::Gcode(_)
+            | OctoCmd::Cooldown
+            | OctoCmd::SetStealth(_)
             // | OctoCmd::ChangeFilament(_) 
-            => "api/printer/command",
-            _ => unimplemented!(),
+            => "api/printer/command".to_string(),
+            OctoCmd::PauseJob | OctoCmd::ResumeJob | OctoCmd::CancelJob => "api/job".to_string(),
+            OctoCmd::PrintFile(path) => format!("api/files/local/{}", path),
         };
 
-        let url = format!("http://{}:5000/{}", host, url);
+        let url = self.url(&url)?;
 
         let cmd = cmd.to_json();
 
@@ -168,7 +243,7 @@ impl OctoClientLocal {
 
         let res = self
             .client
-            .post(&url)
+            .post(url)
             .header("Content-Type", "application/json")
             .header("X-Api-Key", token)
             .json(&cmd)
@@ -176,30 +251,78 @@ impl OctoClientLocal {
             .await?;
 
         if !res.status().is_success() {
-            warn!("status {:#?}", res.status());
-            bail!("Failed to get response, url = {}", url);
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            warn!("status {:#?}, body = {}", status, body);
+            bail!("command rejected: {}, {}", status, body);
         }
 
         // let json: serde_json::Value = res.json().await?;
 
         // Ok(res.json().await?)
-        // Ok(())
-        Ok(res)
+        Ok(())
     }
 }
 
 /// get info
 impl OctoClientLocal {
-    pub async fn get_job_info(&self) -> Result<()> {
-        let v: serde_json::Value = self.get_response("api/job").await?;
+    pub async fn get_job(&self) -> Result<octo_types::job::JobResponse> {
+        self.get_response("api/job").await
+    }
+
+    /// None while OctoPrint isn't connected to the printer
+    pub async fn get_printer_state(
+        &self,
+    ) -> Result<Option<octo_types::printer_status::PrinterStatus>> {
+        let url = self.url("api/printer")?;
+        let res = self.get(url.clone()).await?;
+
+        if res.status() == reqwest::StatusCode::CONFLICT {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            warn!("status {:#?}", res.status());
+            bail!("Failed to get response, url = {}", url);
+        }
 
-        debug!("job info: {:#?}", v);
+        Ok(Some(res.json().await?))
+    }
 
-        unimplemented!()
+    /// a session for the push socket, using the API key
+    pub async fn login(&self) -> Result<octo_types::push::Login> {
+        let url = self.url("api/login")?;
+        let res = self
+            .client
+            .post(url)
+            .header("X-Api-Key", &self.printer_cfg.token)
+            .json(&serde_json::json!({ "passive": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json().await?)
+    }
+
+    pub fn push_url(&self) -> Result<reqwest::Url> {
+        let mut url = self.url("sockjs/websocket")?;
+        url.set_scheme("ws")
+            .map_err(|_| anyhow!("bad push url: {}", url))?;
+        Ok(url)
     }
 
-    pub async fn get_printer_state(&self) -> Result<octo_types::printer_status::PrinterStatus> {
-        let v: octo_types::printer_status::PrinterStatus = self.get_response("api/printer").await?;
-        Ok(v)
+    /// from the `thumbnail` field thumbnail plugins add to the file's metadata
+    pub async fn get_thumbnail(&self, path: &str) -> Result<Vec<u8>> {
+        let info: octo_types::files::FileInfo = self
+            .get_response(&format!("api/files/local/{}", path))
+            .await?;
+        let Some(thumbnail) = info.thumbnail else {
+            bail!(
+                "no thumbnail for {:?}, is a thumbnail plugin installed?",
+                path
+            );
+        };
+
+        let url = self.url("")?.join(&thumbnail)?;
+        let res = self.get(url).await?.error_for_status()?;
+        Ok(res.bytes().await?.to_vec())
     }
 }
@@ -3,6 +3,11 @@ use tracing::{debug, error, info, trace, warn};
 
 use serde_json::Value;
 
+use crate::conn_manager::{
+    motion::MotionCmd,
+    toolchanger::{FilamentType, ToolchangerCmd},
+};
+
 /// pick tool:
 ///     G27 P0 Z5
 ///     T[tool] S1 L0 D0
@@ -16,7 +21,11 @@ use serde_json::Value;
 ///     Unload T0, then load PLA:
 ///         G27 P0 Z40
 ///         M1600 S"PLA" T0 R
+/// tool offsets:
+///     M218 T[tool] X.. Y.. Z..
 /// cooldown:
+///     M104 S0
+///     M140 S0
 /// stealth:
 ///     disable: M9140
 ///     enable: M9150
@@ -39,49 +48,93 @@ pub enum OctoCmd {
         z: bool,
     },
     SetFeedrate(u64),
-    ChangeFilament([Option<ChangeFilament>; 5]),
+    ChangeFilament(Vec<ChangeFilament>),
+    /// nozzle and bed heaters off
     Cooldown,
+    /// quiet mode, on Prusa firmware
     SetStealth(bool),
+    /// raw G-code lines
+    Gcode(Vec<String>),
+    PauseJob,
+    ResumeJob,
+    CancelJob,
+    /// selects and starts a file in local storage, by its path relative to the uploads folder
+    PrintFile(String),
 }
 
 impl OctoCmd {
     pub fn unload_filament(tool: usize) -> Self {
-        let mut out = [None; 5];
-        out[tool] = Some(ChangeFilament::Unload(tool));
-        Self::ChangeFilament(out)
+        Self::ChangeFilament(vec![ChangeFilament::Unload(tool)])
     }
 
-    pub fn load_pla(tools: impl IntoIterator<Item = usize>) -> Self {
-        let mut out = [None; 5];
-        for t in tools.into_iter() {
-            if t >= 5 {
-                continue;
-            }
-            out[t] = Some(ChangeFilament::Load(t, FilamentType::PLA));
+    pub fn load_filament(
+        tools: impl IntoIterator<Item = usize>,
+        filament: FilamentType,
+        temp: Option<f32>,
+    ) -> Self {
+        Self::ChangeFilament(
+            tools
+                .into_iter()
+                .map(|t| ChangeFilament::Load(t, filament.clone(), temp))
+                .collect(),
+        )
+    }
+
+    pub fn motion(cmd: &MotionCmd) -> Self {
+        match cmd {
+            MotionCmd::Jog { x, y, z, .. } => Self::Jog {
+                x: *x,
+                y: *y,
+                z: *z,
+                absolute: false,
+                speed: cmd.jog_speed() as u64,
+            },
+            MotionCmd::Home { x, y, z } if !(*x || *y || *z) => Self::Home {
+                x: true,
+                y: true,
+                z: true,
+            },
+            MotionCmd::Home { x, y, z } => Self::Home {
+                x: *x,
+                y: *y,
+                z: *z,
+            },
+        }
+    }
+
+    pub fn toolchanger(cmd: &ToolchangerCmd) -> Self {
+        match cmd {
+            ToolchangerCmd::PickupTool { tool } => Self::PickupTool(*tool),
+            ToolchangerCmd::ParkTool => Self::ParkTool,
+            ToolchangerCmd::LoadFilament {
+                tool,
+                filament,
+                temp,
+            } => Self::load_filament([*tool], filament.clone(), *temp),
+            ToolchangerCmd::UnloadFilament { tool } => Self::unload_filament(*tool),
+            ToolchangerCmd::SetOffset { tool, x, y, z } => Self::Gcode(vec![format!(
+                "M218 T{} X{:.3} Y{:.3} Z{:.3}",
+                tool, x, y, z
+            )]),
         }
-        Self::ChangeFilament(out)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ChangeFilament {
     Unload(usize),
-    Load(usize, FilamentType),
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum FilamentType {
-    PLA,
-    PETG,
+    /// tool, filament, nozzle temperature to wait for before loading
+    Load(usize, FilamentType, Option<f32>),
 }
 
 impl OctoCmd {
     pub fn to_json(&self) -> Value {
         match self {
+            /// `P0` parks whichever tool is picked up, on Prusa firmware
             OctoCmd::ParkTool => serde_json::json!({
                 "commands": [
                     "G27 P0 Z5",
-                    "T5 S1 L0 D0",
+                    "P0 S1 L0 D0",
                 ],
             }),
             OctoCmd::PickupTool(t) => {
@@ -99,7 +152,19 @@ impl OctoCmd {
                 z,
                 absolute,
                 speed,
-            } => todo!(),
+            } => {
+                let mut out = serde_json::json!({
+                    "command": "jog",
+                    "absolute": absolute,
+                    "speed": speed,
+                });
+                for (axis, dist) in [("x", x), ("y", y), ("z", z)] {
+                    if let Some(dist) = dist {
+                        out[axis] = serde_json::json!(dist);
+                    }
+                }
+                out
+            }
             OctoCmd::Home { x, y, z } => {
                 let mut active_axes = Vec::new();
                 if *x {
@@ -116,36 +181,56 @@ impl OctoCmd {
                     "axes": active_axes,
                 })
             }
-            OctoCmd::SetFeedrate(f) => todo!(),
+            /// percent
+            OctoCmd::SetFeedrate(f) => serde_json::json!({
+                "command": "feedrate",
+                "factor": f,
+            }),
             OctoCmd::ChangeFilament(changes) => {
                 let mut cs = vec![];
 
-                for (i, c) in changes.into_iter().enumerate() {
-                    let Some(c) = c else {
-                        continue;
-                    };
-
+                for c in changes.iter() {
                     match c {
                         ChangeFilament::Unload(tool) => {
                             cs.push(format!("M702 T{} W2", tool));
                         }
-                        ChangeFilament::Load(tool, filament) => {
-                            let f = match filament {
-                                FilamentType::PLA => "PLA",
-                                FilamentType::PETG => "PETG",
-                            };
-                            cs.push(format!("M701 S\"{}\" T{} W2", f, tool));
+                        ChangeFilament::Load(tool, filament, temp) => {
+                            if let Some(temp) = temp {
+                                cs.push(format!("M109 T{} S{:.0}", tool, temp));
+                            }
+                            cs.push(format!("M701 S\"{}\" T{} W2", filament, tool));
                         }
                     }
                 }
 
                 serde_json::json!({
-                    "commands": [
-                        cs
-                    ]
+                    "commands": cs,
                 })
             }
-            _ => todo!(),
+            OctoCmd::Gcode(lines) => serde_json::json!({
+                "commands": lines,
+            }),
+            OctoCmd::PauseJob => serde_json::json!({
+                "command": "pause",
+                "action": "pause",
+            }),
+            OctoCmd::ResumeJob => serde_json::json!({
+                "command": "pause",
+                "action": "resume",
+            }),
+            OctoCmd::CancelJob => serde_json::json!({
+                "command": "cancel",
+            }),
+            OctoCmd::PrintFile(_) => serde_json::json!({
+                "command": "select",
+                "print": true,
+            }),
+            OctoCmd::Cooldown => serde_json::json!({
+                "commands": ["M104 S0", "M140 S0"],
+            }),
+            OctoCmd::SetStealth(enable) => serde_json::json!({
+                "commands": [if *enable { "M9150" } else { "M9140" }],
+            }),
         }
     }
 }
@@ -0,0 +1,411 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use super::{
+    octo_types::push::{Current, Event},
+    OctoClientLocal,
+};
+use crate::status::{
+    terminal::TerminalLine, GenericPrinterStateUpdate, PrinterState, PrinterStateUpdate,
+};
+
+/// OctoPrint's push API on the raw websocket at `sockjs/websocket`.
+/// `current` messages arrive every 500 ms with the full state and any new log lines
+pub struct OctoPush {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    state: PushState,
+}
+
+/// turns push messages into updates, separate from the socket
+#[derive(Debug, Default)]
+struct PushState {
+    /// Finished or Error from a PrintDone or PrintFailed event.
+    /// the flags in `current` only say operational afterwards, so this is kept until they change
+    terminal_state: Option<PrinterState>,
+}
+
+/// what one push message changed
+#[derive(Debug, Default)]
+pub struct PushUpdate {
+    pub update: GenericPrinterStateUpdate,
+    pub terminal: Vec<TerminalLine>,
+}
+
+/// connect
+impl OctoPush {
+    pub async fn connect(client: &OctoClientLocal) -> Result<Self> {
+        let login = client.login().await.context("push login failed")?;
+
+        let url = client.push_url()?;
+        let (mut ws, _) = connect_async(url.as_str()).await?;
+        debug!("octoprint push connected to {}", url);
+
+        let auth = serde_json::json!({
+            "auth": format!("{}:{}", login.name, login.session),
+        });
+        ws.send(Message::Text(auth.to_string().into())).await?;
+
+        let subscribe = serde_json::json!({
+            "subscribe": {
+                "state": {
+                    "logs": true,
+                    "messages": true,
+                },
+                "events": true,
+                "plugins": false,
+            },
+        });
+        ws.send(Message::Text(subscribe.to_string().into())).await?;
+
+        Ok(Self {
+            ws,
+            state: PushState::default(),
+        })
+    }
+}
+
+/// messages
+impl OctoPush {
+    /// waits for the next message that changes anything, errors once the socket closes
+    pub async fn next(&mut self) -> Result<PushUpdate> {
+        loop {
+            let msg = match self.ws.next().await {
+                Some(msg) => msg?,
+                None => bail!("push socket closed"),
+            };
+
+            let text = match msg {
+                Message::Text(text) => text,
+                Message::Ping(data) => {
+                    self.ws.send(Message::Pong(data)).await?;
+                    continue;
+                }
+                Message::Close(frame) => bail!("push socket closed: {:?}", frame),
+                _ => continue,
+            };
+
+            let json: serde_json::Map<String, Value> = match serde_json::from_str(text.as_str()) {
+                Ok(json) => json,
+                Err(e) => {
+                    trace!("Failed to parse JSON: {}", e);
+                    continue;
+                }
+            };
+
+            let out = self.state.handle_message(json)?;
+            if !out.update.0.is_empty() || !out.terminal.is_empty() {
+                return Ok(out);
+            }
+        }
+    }
+}
+
+/// MARK: PushState
+impl PushState {
+    fn handle_message(&mut self, json: serde_json::Map<String, Value>) -> Result<PushUpdate> {
+        let mut out = PushUpdate::default();
+        for (kind, payload) in json {
+            match kind.as_str() {
+                /// `history` is sent once after connecting, with the same fields as `current`
+                "current" | "history" => {
+                    let current: Current = match serde_json::from_value(payload) {
+                        Ok(current) => current,
+                        Err(e) => {
+                            warn!("octoprint push: bad {} message: {:?}", kind, e);
+                            continue;
+                        }
+                    };
+                    self.handle_current(current, kind == "history", &mut out);
+                }
+                "event" => {
+                    let event: Event = match serde_json::from_value(payload) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            warn!("octoprint push: bad event: {:?}", e);
+                            continue;
+                        }
+                    };
+                    out.update.0.extend(self.handle_event(&event));
+                }
+                /// the session expired, a new login is needed
+                "reauthRequired" => bail!("push reauth required: {}", payload),
+                _ => trace!("octoprint push: {}", kind),
+            }
+        }
+        Ok(out)
+    }
+
+    fn handle_current(&mut self, current: Current, history: bool, out: &mut PushUpdate) {
+        /// `history` has the server's temperature backlog, for the graphs
+        if history && current.temps.len() > 1 {
+            let progress = current.progress.completion.unwrap_or(0.);
+            out.update.0.push(PrinterStateUpdate::TempHistory(
+                current
+                    .temps
+                    .iter()
+                    .map(|t| t.to_sample(progress))
+                    .collect(),
+            ));
+        }
+
+        /// only the newest reading matters, older ones were already sent
+        let temperature = current.temps.last().map(|t| t.to_temperature());
+
+        let mut updates = OctoClientLocal::status_updates(
+            &current.state,
+            temperature.as_ref(),
+            &current.job,
+            &current.progress,
+        );
+        self.keep_terminal_state(&mut updates);
+        out.update.0.extend(updates);
+
+        /// replies to M114, which is sent after jogging
+        if let Some(pos) = current
+            .logs
+            .iter()
+            .rev()
+            .find_map(|l| Self::parse_position(l))
+        {
+            out.update.0.push(PrinterStateUpdate::Position(pos));
+        }
+
+        let messages = current.messages.into_iter().map(|l| (l, true));
+        let logs = current.logs.into_iter().map(|l| (l, false));
+        out.terminal
+            .extend(logs.chain(messages).map(|(l, m)| TerminalLine::new(l, m)));
+    }
+
+    /// `Recv: X:10.00 Y:20.00 Z:5.00 E:0.00 Count X:800 Y:1600 Z:2000`,
+    /// the steps after `Count` are ignored
+    fn parse_position(line: &str) -> Option<[f32; 3]> {
+        let line = line.strip_prefix("Recv:")?.trim();
+        if !line.starts_with("X:") {
+            return None;
+        }
+
+        let mut pos = [None; 3];
+        for word in line.split_whitespace().take_while(|w| *w != "Count") {
+            let Some((axis, value)) = word.split_once(':') else {
+                continue;
+            };
+            let idx = match axis {
+                "X" => 0,
+                "Y" => 1,
+                "Z" => 2,
+                _ => continue,
+            };
+            pos[idx] = value.parse::<f32>().ok();
+        }
+
+        Some([pos[0]?, pos[1]?, pos[2]?])
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Vec<PrinterStateUpdate> {
+        let updates = Self::event_updates(event);
+        for u in updates.iter() {
+            if let PrinterStateUpdate::State(state) = u {
+                self.terminal_state =
+                    matches!(state, PrinterState::Finished | PrinterState::Error(_))
+                        .then(|| state.clone());
+            }
+        }
+        updates
+    }
+
+    /// replaces Idle from the flags with the held terminal state, until a new print starts
+    /// or the printer goes away
+    fn keep_terminal_state(&mut self, updates: &mut [PrinterStateUpdate]) {
+        let Some(terminal) = self.terminal_state.as_ref() else {
+            return;
+        };
+        for u in updates.iter_mut() {
+            let PrinterStateUpdate::State(state) = u else {
+                continue;
+            };
+            if matches!(state, PrinterState::Idle | PrinterState::Unknown(_)) {
+                *state = terminal.clone();
+            } else {
+                self.terminal_state = None;
+                return;
+            }
+        }
+    }
+
+    /// events arrive as they happen, so short states aren't missed between `current` messages
+    fn event_updates(event: &Event) -> Vec<PrinterStateUpdate> {
+        let payload = |key: &str| {
+            event
+                .payload
+                .as_ref()
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string())
+        };
+
+        match event.kind.as_str() {
+            "PrintStarted" => {
+                let mut out = vec![
+                    PrinterStateUpdate::State(PrinterState::Printing),
+                    PrinterStateUpdate::Progress(0.),
+                ];
+                if let Some(path) = payload("path").or(payload("name")) {
+                    out.push(PrinterStateUpdate::CurrentFile(path));
+                }
+                out
+            }
+            "PrintDone" => vec![
+                PrinterStateUpdate::State(PrinterState::Finished),
+                PrinterStateUpdate::Progress(100.),
+            ],
+            /// followed by PrintCancelled or an Error event
+            "PrintFailed" => match payload("reason").as_deref() {
+                Some("cancelled") => vec![PrinterStateUpdate::State(PrinterState::Idle)],
+                _ => vec![PrinterStateUpdate::State(PrinterState::Error(Some(
+                    "Print failed".to_string(),
+                )))],
+            },
+            "PrintPaused" => vec![PrinterStateUpdate::State(PrinterState::Paused)],
+            "PrintResumed" => vec![PrinterStateUpdate::State(PrinterState::Printing)],
+            "Error" => vec![PrinterStateUpdate::State(PrinterState::Error(payload(
+                "error",
+            )))],
+            "Disconnected" => vec![PrinterStateUpdate::State(PrinterState::Disconnected)],
+            _ => {
+                trace!("octoprint event: {:?}", event.kind);
+                vec![]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn current(printing: bool) -> Value {
+        json!({
+            "state": {
+                "text": if printing { "Printing" } else { "Operational" },
+                "flags": {
+                    "operational": true,
+                    "paused": false,
+                    "printing": printing,
+                    "cancelling": false,
+                    "pausing": false,
+                    "sdReady": false,
+                    "error": false,
+                    "ready": !printing,
+                    "closedOrError": false,
+                },
+            },
+            "progress": {"completion": 50.0},
+            "temps": [],
+        })
+    }
+
+    fn handle(state: &mut PushState, msg: Value) -> Vec<PrinterStateUpdate> {
+        let Value::Object(msg) = msg else {
+            panic!("not an object");
+        };
+        state.handle_message(msg).unwrap().update.0
+    }
+
+    fn states(updates: &[PrinterStateUpdate]) -> Vec<PrinterState> {
+        updates
+            .iter()
+            .filter_map(|u| match u {
+                PrinterStateUpdate::State(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finished_survives_current() {
+        let mut state = PushState::default();
+
+        let u = handle(&mut state, json!({"event": {"type": "PrintDone"}}));
+        assert_eq!(states(&u), [PrinterState::Finished]);
+
+        let u = handle(&mut state, json!({"current": current(false)}));
+        assert_eq!(states(&u), [PrinterState::Finished]);
+
+        /// until the next print starts
+        let u = handle(&mut state, json!({"current": current(true)}));
+        assert_eq!(states(&u), [PrinterState::Printing]);
+        let u = handle(&mut state, json!({"current": current(false)}));
+        assert_eq!(states(&u), [PrinterState::Idle]);
+    }
+
+    #[test]
+    fn failed_survives_current_unless_cancelled() {
+        let mut state = PushState::default();
+
+        handle(
+            &mut state,
+            json!({"event": {"type": "PrintFailed", "payload": {"reason": "error"}}}),
+        );
+        let u = handle(&mut state, json!({"current": current(false)}));
+        assert!(matches!(states(&u)[..], [PrinterState::Error(_)]));
+
+        handle(&mut state, json!({"event": {"type": "PrintStarted"}}));
+        handle(
+            &mut state,
+            json!({"event": {"type": "PrintFailed", "payload": {"reason": "cancelled"}}}),
+        );
+        let u = handle(&mut state, json!({"current": current(false)}));
+        assert_eq!(states(&u), [PrinterState::Idle]);
+    }
+
+    #[test]
+    fn bad_payload_is_skipped() {
+        let mut state = PushState::default();
+        let u = handle(
+            &mut state,
+            json!({"current": {"state": "broken"}, "event": {"type": "PrintPaused"}}),
+        );
+        assert_eq!(states(&u), [PrinterState::Paused]);
+    }
+
+    #[test]
+    fn history_backlog() {
+        let mut msg = current(false);
+        msg["temps"] = json!([
+            {"time": 100, "tool0": {"actual": 150.0, "target": 210.0}, "bed": {"actual": 40.0, "target": 60.0}},
+            {"time": 102, "tool0": {"actual": 180.0, "target": 210.0}, "bed": {"actual": 50.0, "target": 60.0}},
+            {"time": 104, "tool0": {"actual": 209.5, "target": 210.0}, "bed": {"actual": 59.0, "target": 60.0}},
+        ]);
+
+        let mut state = PushState::default();
+        let u = handle(&mut state, json!({"history": msg.clone()}));
+        let backlog = u
+            .iter()
+            .find_map(|u| match u {
+                PrinterStateUpdate::TempHistory(samples) => Some(samples),
+                _ => None,
+            })
+            .expect("no backlog");
+        let nozzle = backlog
+            .iter()
+            .map(|s| (s.time, s.nozzle))
+            .collect::<Vec<_>>();
+        assert_eq!(nozzle, [(100., 150.), (102., 180.), (104., 209.5)]);
+        assert_eq!(backlog[0].bed_target, 60.);
+
+        /// `current` only sends new readings
+        let u = handle(&mut state, json!({"current": msg}));
+        assert!(!u
+            .iter()
+            .any(|u| matches!(u, PrinterStateUpdate::TempHistory(_))));
+        assert!(u
+            .iter()
+            .any(|u| matches!(u, PrinterStateUpdate::NozzleTemp(None, t) if *t == 209.5)));
+    }
+}
@@ -94,50 +94,172 @@ pub mod printer_status {
     }
 }
 
+/// `api/job`, everything is null when no file is selected
 pub mod job {
     use serde::{Deserialize, Serialize};
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct JobResponse {
         pub job: Job,
         pub progress: Progress,
         pub state: String,
+        pub error: Option<String>,
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Job {
         pub file: File,
+        /// seconds
         #[serde(rename = "estimatedPrintTime")]
-        pub estimated_print_time: i64,
-        pub filament: Filament,
+        pub estimated_print_time: Option<f64>,
+        pub filament: Option<Filament>,
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct File {
-        pub name: String,
-        pub origin: String,
-        pub size: i64,
-        pub date: i64,
+        pub name: Option<String>,
+        /// relative to the origin, e.g. "folder/part.gcode"
+        pub path: Option<String>,
+        pub display: Option<String>,
+        /// "local" or "sdcard"
+        pub origin: Option<String>,
+        pub size: Option<i64>,
+        pub date: Option<i64>,
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Filament {
-        pub tool0: Tool0,
+        pub tool0: Option<Tool0>,
     }
 
+    /// mm, cm³
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Tool0 {
-        pub length: i64,
-        pub volume: f32,
+        pub length: f64,
+        pub volume: f64,
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Progress {
-        pub completion: f32,
-        pub filepos: i64,
+        /// percent
+        pub completion: Option<f32>,
+        pub filepos: Option<i64>,
+        /// seconds
         #[serde(rename = "printTime")]
-        pub print_time: i64,
+        pub print_time: Option<i64>,
         #[serde(rename = "printTimeLeft")]
-        pub print_time_left: i64,
+        pub print_time_left: Option<i64>,
+    }
+}
+
+/// `api/files/{origin}/{path}`, only the fields used for thumbnails
+pub mod files {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct FileInfo {
+        pub name: String,
+        pub path: String,
+        /// added by thumbnail plugins such as PrusaSlicer Thumbnails,
+        /// relative to the server, e.g. "plugin/prusaslicerthumbnails/thumbnail/part.png?20240101"
+        pub thumbnail: Option<String>,
+    }
+}
+
+/// messages from the push socket at `sockjs/websocket`
+pub mod push {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    /// `current` and `history` messages, `logs` and `messages` only hold new lines
+    #[derive(Default, Debug, Clone, Deserialize)]
+    #[serde(default)]
+    pub struct Current {
+        pub state: super::printer_status::State,
+        pub job: super::job::Job,
+        pub progress: super::job::Progress,
+        pub temps: Vec<Temps>,
+        pub logs: Vec<String>,
+        pub messages: Vec<String>,
+    }
+
+    /// one temperature reading, heaters are keyed by name, e.g. "tool0", "bed"
+    #[derive(Default, Debug, Clone, Deserialize)]
+    pub struct Temps {
+        #[serde(default)]
+        pub time: i64,
+        #[serde(flatten)]
+        pub heaters: HashMap<String, Heater>,
+    }
+
+    #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Heater {
+        pub actual: Option<f32>,
+        pub target: Option<f32>,
+    }
+
+    impl Temps {
+        pub fn to_temperature(&self) -> super::printer_status::Temperature {
+            let tool = |h: &Heater| super::printer_status::Tool {
+                actual: h.actual.unwrap_or(0.),
+                target: h.target,
+                offset: 0,
+            };
+
+            let mut tools = self
+                .heaters
+                .iter()
+                .filter_map(|(k, h)| {
+                    let id = k.strip_prefix("tool")?.parse::<usize>().ok()?;
+                    Some((id, tool(h)))
+                })
+                .collect::<Vec<_>>();
+            tools.sort_by_key(|t| t.0);
+
+            super::printer_status::Temperature {
+                tools,
+                bed: self.heaters.get("bed").map(tool).unwrap_or_default(),
+                history: vec![],
+            }
+        }
+
+        /// for the temperature graphs, `progress` is the same for the whole backlog
+        pub fn to_sample(&self, progress: f32) -> crate::status::history::Sample {
+            let heater = |name: &str| self.heaters.get(name).cloned().unwrap_or_default();
+            let (tool, bed, chamber) = (heater("tool0"), heater("bed"), heater("chamber"));
+
+            crate::status::history::Sample {
+                time: self.time as f64,
+                nozzle: tool.actual.unwrap_or(0.),
+                nozzle_target: tool.target.unwrap_or(0.),
+                bed: bed.actual.unwrap_or(0.),
+                bed_target: bed.target.unwrap_or(0.),
+                chamber: chamber.actual.unwrap_or(0.),
+                chamber_target: chamber.target,
+                progress,
+            }
+        }
+    }
+
+    #[derive(Default, Debug, Clone, Deserialize)]
+    #[serde(default)]
+    pub struct Event {
+        #[serde(rename = "type")]
+        pub kind: String,
+        pub payload: Option<serde_json::Value>,
+    }
+
+    #[derive(Default, Debug, Clone, Deserialize)]
+    pub struct Login {
+        pub name: String,
+        pub session: String,
     }
 }
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{
+    octo_commands::OctoCmd,
+    octo_push::{OctoPush, PushUpdate},
+    OctoClientLocal,
+};
+use crate::{
+    config::{printer_config::PrinterConfigOcto, printer_id::PrinterId},
+    conn_manager::{worker_message::WorkerMsg, CommandId, CommandResult, WorkerCmd},
+    status::{GenericPrinterStateUpdate, PrinterState, PrinterStateUpdate},
+};
+
+/// a standalone `[[octoprint]]` printer, using the push socket
+/// and polling while it isn't connected
+pub struct OctoWorker {
+    printer_cfg: Arc<RwLock<PrinterConfigOcto>>,
+    client: OctoClientLocal,
+    push: Option<OctoPush>,
+    last_push_attempt: Option<std::time::Instant>,
+    tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
+    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
+    kill_rx: tokio::sync::oneshot::Receiver<()>,
+    update_timer: tokio::time::Interval,
+}
+
+/// new, run
+impl OctoWorker {
+    /// between attempts to connect the push socket
+    const PUSH_RETRY: std::time::Duration = std::time::Duration::from_secs(30);
+
+    pub async fn new(
+        printer_cfg: Arc<RwLock<PrinterConfigOcto>>,
+        tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
+        kill_rx: tokio::sync::oneshot::Receiver<()>,
+        interval: Option<std::time::Duration>,
+    ) -> Result<Self> {
+        let client = OctoClientLocal::new(printer_cfg.read().await.clone())?;
+
+        let update_timer = if let Some(interval) = interval {
+            tokio::time::interval(interval)
+        } else {
+            tokio::time::interval(std::time::Duration::from_secs(1))
+        };
+
+        Ok(Self {
+            printer_cfg,
+            client,
+            push: None,
+            last_push_attempt: None,
+            tx,
+            cmd_rx,
+            kill_rx,
+            update_timer,
+        })
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = self.update_timer.tick() => {
+                    if self.push.is_none() {
+                        self.update().await?;
+                        self.connect_push().await;
+                    }
+                }
+                msg = Self::next_push(&mut self.push) => {
+                    match msg {
+                        Ok(msg) => self.send_push_update(msg).await?,
+                        Err(e) => {
+                            warn!("octoprint push disconnected, polling: {:?}", e);
+                            self.push = None;
+                        }
+                    }
+                }
+                _ = &mut self.kill_rx => {
+                    info!("kill_rx fired, exiting");
+                    return Ok(());
+                }
+                Some((cmd_id, cmd)) = self.cmd_rx.recv() => {
+                    debug!("got worker command: {:?}", cmd);
+                    /// HTTP commands complete synchronously
+                    let result = match self.handle_command(cmd).await {
+                        Ok(()) => CommandResult::Acked,
+                        Err(e) => {
+                            error!("error handling command: {:?}", e);
+                            CommandResult::Rejected(e.to_string())
+                        }
+                    };
+                    let id = self.printer_cfg.read().await.id.clone();
+                    self.tx.send((id, WorkerMsg::CommandResult(cmd_id, result)))?;
+                }
+            }
+        }
+    }
+
+    async fn update(&mut self) -> Result<()> {
+        let id = self.printer_cfg.read().await.id.clone();
+
+        let update = match self.client.get_update().await {
+            Ok(update) => update,
+            /// server unreachable, keep polling until it's back
+            Err(e) => {
+                trace!("octoprint update failed: {:?}", e);
+                GenericPrinterStateUpdate(vec![PrinterStateUpdate::State(
+                    PrinterState::Disconnected,
+                )])
+            }
+        };
+        self.tx.send((id, WorkerMsg::StatusUpdate(update)))?;
+
+        Ok(())
+    }
+}
+
+/// push
+impl OctoWorker {
+    async fn connect_push(&mut self) {
+        if self
+            .last_push_attempt
+            .is_some_and(|t| t.elapsed() < Self::PUSH_RETRY)
+        {
+            return;
+        }
+        self.last_push_attempt = Some(std::time::Instant::now());
+
+        match OctoPush::connect(&self.client).await {
+            Ok(push) => {
+                info!("octoprint push connected: {}", self.client.printer_cfg.name);
+                self.push = Some(push);
+            }
+            Err(e) => debug!("octoprint push unavailable, polling: {:?}", e),
+        }
+    }
+
+    /// pending forever while there's no push socket
+    async fn next_push(push: &mut Option<OctoPush>) -> Result<PushUpdate> {
+        match push {
+            Some(push) => push.next().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn send_push_update(&mut self, msg: PushUpdate) -> Result<()> {
+        let id = self.printer_cfg.read().await.id.clone();
+
+        if !msg.update.0.is_empty() {
+            self.tx
+                .send((id.clone(), WorkerMsg::StatusUpdate(msg.update)))?;
+        }
+        if !msg.terminal.is_empty() {
+            self.tx.send((id, WorkerMsg::Terminal(msg.terminal)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// commands
+impl OctoWorker {
+    async fn handle_command(&mut self, cmd: WorkerCmd) -> Result<()> {
+        let cmd = match cmd {
+            WorkerCmd::Pause => OctoCmd::PauseJob,
+            WorkerCmd::Resume => OctoCmd::ResumeJob,
+            WorkerCmd::Cancel => OctoCmd::CancelJob,
+            WorkerCmd::PrintFile(path) => OctoCmd::PrintFile(path.trim_start_matches('/').into()),
+            WorkerCmd::SetNozzleTemp(tool, temp) => OctoCmd::Gcode(vec![match tool {
+                Some(t) => format!("M104 T{} S{:.0}", t, temp),
+                None => format!("M104 S{:.0}", temp),
+            }]),
+            WorkerCmd::SetBedTemp(temp) => OctoCmd::Gcode(vec![format!("M140 S{:.0}", temp)]),
+            WorkerCmd::SetFan(speed) => {
+                let speed = (speed.clamp(0., 1.) * 255.).round() as u8;
+                OctoCmd::Gcode(vec![format!("M106 S{}", speed)])
+            }
+            WorkerCmd::Gcode(gcode) => OctoCmd::Gcode(
+                gcode
+                    .lines()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .map(|l| l.to_string())
+                    .collect(),
+            ),
+            WorkerCmd::SetLight(_) => bail!("light control not supported on octoprint"),
+            WorkerCmd::SetAmsFilament(_) => bail!("AMS not supported on octoprint"),
+            WorkerCmd::PrintProjectFile(_) => bail!("project files not supported on octoprint"),
+            WorkerCmd::EmergencyStop | WorkerCmd::FirmwareRestart => {
+                bail!("{:?} not supported on octoprint", cmd)
+            }
+            /// the tool G-code is Prusa firmware specific, use a `[[prusa]]` printer with `octo`
+            WorkerCmd::Toolchanger(_) => bail!("toolchanger not supported on octoprint"),
+            WorkerCmd::Motion(motion) => {
+                self.client.send_command(&OctoCmd::motion(&motion)).await?;
+                /// the reply shows up in the push log, which updates the position
+                OctoCmd::Gcode(vec!["M114".to_string()])
+            }
+        };
+
+        self.client.send_command(&cmd).await
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use reqwest::{RequestBuilder, Response, Url};
+
+use super::prusa_local_types::{FileEntry, StorageList, Version};
+use crate::{config::printer_config::PrinterConfigPrusa, conn_manager::files::RemoteFile};
+
+/// PrusaLink v1 storage API.
+/// paths start with the storage, e.g. "/usb/shape.bgcode"
+pub struct PrusaFiles {
+    client: reqwest::Client,
+    host: String,
+    key: String,
+}
+
+/// new, requests
+impl PrusaFiles {
+    const URL_VERSION: &'static str = "api/version";
+    const URL_STORAGE: &'static str = "api/v1/storage";
+    const URL_FILES: &'static str = "api/v1/files";
+    /// OctoPrint compatible upload, for firmware without upload-by-put
+    const URL_FILES_LEGACY: &'static str = "api/files/local";
+
+    pub fn new(client: reqwest::Client, printer: &PrinterConfigPrusa) -> Self {
+        Self {
+            client,
+            host: printer.host.clone(),
+            key: printer.key.clone(),
+        }
+    }
+
+    /// `base` followed by each segment of `path`, percent-encoded
+    fn url(&self, base: &str, path: &str) -> Result<Url> {
+        let mut url = Url::parse(&format!("http://{}/{}", self.host, base))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("bad url: {}", base))?
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        Ok(url)
+    }
+
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let resp = req.header("X-Api-Key", &self.key).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            debug!("status {:#?}, body = {}", status, body);
+            bail!("PrusaLink: {}, {}", status, body.trim());
+        }
+        Ok(resp)
+    }
+
+    pub async fn version(&self) -> Result<Version> {
+        let url = self.url(Self::URL_VERSION, "")?;
+        Ok(self.send(self.client.get(url)).await?.json().await?)
+    }
+}
+
+/// files
+impl PrusaFiles {
+    /// storages at "/", otherwise the contents of a folder
+    pub async fn list(&self, dir: &str) -> Result<Vec<RemoteFile>> {
+        if dir.trim_matches('/').is_empty() {
+            let url = self.url(Self::URL_STORAGE, "")?;
+            let storages: StorageList = self.send(self.client.get(url)).await?.json().await?;
+            return Ok(storages
+                .storage_list
+                .into_iter()
+                .map(|s| RemoteFile {
+                    path: s.path,
+                    display_name: None,
+                    size: None,
+                    is_dir: true,
+                    printable: false,
+                })
+                .collect());
+        }
+
+        let url = self.url(Self::URL_FILES, dir)?;
+        let folder: FileEntry = self.send(self.client.get(url)).await?.json().await?;
+        ensure!(folder.kind == "FOLDER", "not a folder: {:?}", dir);
+
+        let dir = dir.trim_end_matches('/');
+        Ok(folder
+            .children
+            .into_iter()
+            .map(|f| RemoteFile {
+                path: format!("{}/{}", dir, f.name),
+                display_name: f.display_name,
+                size: f.size,
+                is_dir: f.kind == "FOLDER",
+                printable: f.kind == "PRINT_FILE",
+            })
+            .collect())
+    }
+
+    /// returns the path of the uploaded file
+    pub async fn upload(&self, dir: &str, name: &str, data: Vec<u8>) -> Result<String> {
+        let path = format!("{}/{}", dir.trim_end_matches('/'), name);
+        debug!("prusa uploading {:?}, {} bytes", path, data.len());
+
+        if self.version().await?.capabilities.upload_by_put {
+            let url = self.url(Self::URL_FILES, &path)?;
+            let req = self
+                .client
+                .put(url)
+                .header("Content-Type", "application/octet-stream")
+                .header("Overwrite", "?1")
+                .header("Print-After-Upload", "?0")
+                .body(data);
+            self.send(req).await?;
+        } else {
+            self.upload_legacy(dir, name, data).await?;
+        }
+
+        Ok(path)
+    }
+
+    /// multipart POST, the folder is relative to the storage
+    async fn upload_legacy(&self, dir: &str, name: &str, data: Vec<u8>) -> Result<()> {
+        let folder = dir
+            .trim_matches('/')
+            .split_once('/')
+            .map(|(_, rest)| rest)
+            .unwrap_or_default();
+
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(name.to_string())
+            .mime_str("application/octet-stream")?;
+        let form = reqwest::multipart::Form::new()
+            .text("path", folder.to_string())
+            .part("file", part);
+
+        let url = self.url(Self::URL_FILES_LEGACY, "")?;
+        let req = self.client.post(url).multipart(form);
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn download(&self, path: &str) -> Result<Vec<u8>> {
+        let url = self.url(Self::URL_FILES, path)?;
+        let file: FileEntry = self.send(self.client.get(url)).await?.json().await?;
+        let download = file
+            .refs
+            .and_then(|r| r.download)
+            .with_context(|| format!("no download link for {:?}", path))?;
+
+        debug!("prusa downloading {:?}", download);
+        let url = Url::parse(&format!("http://{}", self.host))?.join(&download)?;
+        let bytes = self.send(self.client.get(url)).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<()> {
+        debug!("prusa deleting {:?}", path);
+        let url = self.url(Self::URL_FILES, path)?;
+        self.send(self.client.delete(url)).await?;
+        Ok(())
+    }
+
+    /// fails with 409 Conflict if the printer is busy
+    pub async fn print(&self, path: &str) -> Result<()> {
+        debug!("prusa printing {:?}", path);
+        let url = self.url(Self::URL_FILES, path)?;
+        self.send(self.client.post(url)).await?;
+        Ok(())
+    }
+}
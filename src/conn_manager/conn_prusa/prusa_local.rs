@@ -10,7 +10,8 @@ use tokio::sync::RwLock;
 use crate::{
     config::{printer_config::PrinterConfigPrusa, printer_id::PrinterId},
     conn_manager::{
-        conn_prusa::prusa_local_types::PrusaStatus, worker_message::WorkerMsg, WorkerCmd,
+        conn_octoprint::octo_commands::OctoCmd, conn_prusa::prusa_local_types::PrusaStatus,
+        worker_message::WorkerMsg, CommandId, CommandResult, WorkerCmd,
     },
     status::{GenericPrinterStateUpdate, PrinterState, PrinterStateUpdate},
 };
@@ -19,7 +20,7 @@ pub struct PrusaClientLocal {
     pub(super) printer_cfg: Arc<RwLock<PrinterConfigPrusa>>,
     client: reqwest::Client,
     tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+    cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
     kill_rx: tokio::sync::oneshot::Receiver<()>,
     update_timer: tokio::time::Interval,
     // thumbnail: Option<(String, Vec<u8>)>,
@@ -36,7 +37,7 @@ impl PrusaClientLocal {
     pub async fn new(
         printer_cfg: Arc<RwLock<PrinterConfigPrusa>>,
         tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
-        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<WorkerCmd>,
+        cmd_rx: tokio::sync::mpsc::UnboundedReceiver<(CommandId, WorkerCmd)>,
         kill_rx: tokio::sync::oneshot::Receiver<()>,
         interval: Option<std::time::Duration>,
     ) -> Result<Self> {
@@ -86,8 +87,18 @@ impl PrusaClientLocal {
                     info!("kill_rx fired, exiting");
                     return Ok(());
                 }
-                Some(cmd) = self.cmd_rx.recv() => {
-                    warn!("unhandled cmd = {:#?}", cmd);
+                Some((cmd_id, cmd)) = self.cmd_rx.recv() => {
+                    debug!("got worker command: {:?}", cmd);
+                    /// HTTP commands complete synchronously
+                    let result = match self.handle_command(cmd).await {
+                        Ok(()) => CommandResult::Acked,
+                        Err(e) => {
+                            error!("error handling command: {:?}", e);
+                            CommandResult::Rejected(e.to_string())
+                        }
+                    };
+                    let id = self.printer_cfg.read().await.id.clone();
+                    self.tx.send((id, WorkerMsg::CommandResult(cmd_id, result)))?;
                 }
             }
         }
@@ -98,16 +109,17 @@ impl PrusaClientLocal {
 
         let (update, status, job) = self.get_update().await?;
         // debug!("sending update: {:#?}", &update);
-        self.tx
-            .send((id.clone(), WorkerMsg::StatusUpdate(update)))?;
-
+        /// sent first so the raw state is current when the manager sees the transition
         self.tx.send((
             id.clone(),
             WorkerMsg::StatusUpdatePrusa(PrusaStatus { status, job }),
         ))?;
 
+        self.tx
+            .send((id.clone(), WorkerMsg::StatusUpdate(update)))?;
+
         if let Some(octo) = &self.octo_client {
-            let update = octo.get_update().await?;
+            let update = octo.get_temperatures().await?;
             self.tx
                 .send((id.clone(), WorkerMsg::StatusUpdate(update)))?;
         }
@@ -116,6 +128,104 @@ impl PrusaClientLocal {
     }
 }
 
+/// commands
+impl PrusaClientLocal {
+    async fn handle_command(&mut self, cmd: WorkerCmd) -> Result<()> {
+        match cmd {
+            WorkerCmd::Pause => self.job_command(reqwest::Method::PUT, "/pause").await,
+            WorkerCmd::Resume => self.job_command(reqwest::Method::PUT, "/resume").await,
+            WorkerCmd::Cancel => self.job_command(reqwest::Method::DELETE, "").await,
+            WorkerCmd::SetLight(_) => bail!("light control not supported on prusa"),
+            WorkerCmd::SetAmsFilament(_) => bail!("AMS not supported on prusa"),
+            WorkerCmd::PrintFile(path) => {
+                let printer = self.printer_cfg.read().await.clone();
+                super::prusa_files::PrusaFiles::new(self.client.clone(), &printer)
+                    .print(&path)
+                    .await
+            }
+            WorkerCmd::PrintProjectFile(_) => bail!("project files not supported on prusa"),
+            WorkerCmd::EmergencyStop | WorkerCmd::FirmwareRestart => {
+                bail!("{:?} not supported on prusa", cmd)
+            }
+            /// PrusaLink has no G-code endpoint, these go through OctoPrint if configured
+            WorkerCmd::SetNozzleTemp(tool, temp) => {
+                let cmd = match tool {
+                    Some(t) => format!("M104 T{} S{:.0}", t, temp),
+                    None => format!("M104 S{:.0}", temp),
+                };
+                self.send_octo_gcode(vec![cmd]).await
+            }
+            WorkerCmd::SetBedTemp(temp) => {
+                self.send_octo_gcode(vec![format!("M140 S{:.0}", temp)])
+                    .await
+            }
+            WorkerCmd::SetFan(speed) => {
+                let speed = (speed.clamp(0., 1.) * 255.).round() as u8;
+                self.send_octo_gcode(vec![format!("M106 S{}", speed)]).await
+            }
+            WorkerCmd::Gcode(gcode) => {
+                let lines = gcode
+                    .lines()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .map(|l| l.to_string())
+                    .collect();
+                self.send_octo_gcode(lines).await
+            }
+            WorkerCmd::Toolchanger(cmd) => {
+                ensure!(
+                    self.printer_cfg.read().await.toolchanger,
+                    "not a toolchanger, set `toolchanger = true` in the config"
+                );
+                self.send_octo_command(&OctoCmd::toolchanger(&cmd)).await
+            }
+            WorkerCmd::Motion(cmd) => self.send_octo_command(&OctoCmd::motion(&cmd)).await,
+        }
+    }
+
+    /// PUT/DELETE on `api/v1/job/{id}{suffix}` for the current job
+    async fn job_command(&self, method: reqwest::Method, suffix: &str) -> Result<()> {
+        let status = self.get_status().await?;
+        let Some(job) = status.job else {
+            bail!("no active job");
+        };
+
+        let host = self.printer_cfg.read().await.host.clone();
+        let url = format!(
+            "http://{}:{}/{}/{}{}",
+            host,
+            80,
+            Self::URL_JOB,
+            job.id,
+            suffix
+        );
+
+        let req = self.set_headers(self.client.request(method, &url)).await?;
+        let resp = req.send().await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            debug!("status {:#?}, body = {}", status, body);
+            bail!("job command failed: {}, {}", status, body);
+        }
+
+        Ok(())
+    }
+
+    async fn send_octo_gcode(&self, lines: Vec<String>) -> Result<()> {
+        self.send_octo_command(&OctoCmd::Gcode(lines)).await
+    }
+
+    async fn send_octo_command(&self, cmd: &OctoCmd) -> Result<()> {
+        let Some(octo) = &self.octo_client else {
+            bail!("G-code commands need an OctoPrint connection");
+        };
+        octo.send_command(cmd).await?;
+        Ok(())
+    }
+}
+
 /// set_headers
 impl PrusaClientLocal {
     async fn set_headers(&self, req: RequestBuilder) -> Result<RequestBuilder> {
@@ -9,6 +9,7 @@ pub struct PrusaStatus {
 
 /// MARK: Version
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Version {
     pub api: String,
     pub server: String,
@@ -99,3 +100,42 @@ pub struct Refs {
     pub icon: String,
     pub thumbnail: String,
 }
+
+/// MARK: Files
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageList {
+    pub storage_list: Vec<StorageInfo>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageInfo {
+    /// e.g. "/usb"
+    pub path: String,
+    pub name: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// `api/v1/files/{storage}/{path}`, a folder lists its children
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// short (8.3) name on some firmware
+    pub name: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// "FOLDER", "PRINT_FILE", "FIRMWARE" or "FILE"
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub children: Vec<FileEntry>,
+    #[serde(default)]
+    pub refs: Option<FileRefs>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRefs {
+    #[serde(default)]
+    pub download: Option<String>,
+}
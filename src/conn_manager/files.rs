@@ -0,0 +1,185 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::{conn_bambu::ftps::BambuFtps, conn_prusa::prusa_files::PrusaFiles, PrinterConnMsg};
+use crate::config::{
+    printer_config::{PrinterConfig, PrinterConfigBambu, PrinterConfigPrusa},
+    printer_id::PrinterId,
+};
+
+/// a file or directory on a printer's storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteFile {
+    /// absolute, e.g. "/cache/cube.gcode.3mf"
+    pub path: String,
+    /// long name, where the path uses a short one
+    pub display_name: Option<String>,
+    pub size: Option<u64>,
+    pub is_dir: bool,
+    /// can be started with WorkerCmd::PrintFile
+    pub printable: bool,
+}
+
+impl RemoteFile {
+    pub fn name(&self) -> &str {
+        self.display_name
+            .as_deref()
+            .unwrap_or_else(|| self.path.rsplit('/').next().unwrap_or(&self.path))
+    }
+}
+
+/// file operations, run by PrinterConnManager on their own connection
+/// so a slow transfer doesn't hold up the printer's worker
+#[derive(Debug, Clone)]
+pub enum FileCmd {
+    /// directory
+    List(String),
+    /// local file, remote directory it's uploaded into
+    Upload(PathBuf, String),
+    /// remote file, local path
+    Download(String, PathBuf),
+    Delete(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileResult {
+    /// directory, entries
+    Listing(String, Vec<RemoteFile>),
+    /// remote path
+    Uploaded(String),
+    /// local path
+    Downloaded(PathBuf),
+    /// remote path
+    Deleted(String),
+}
+
+/// the result is sent back as a PrinterConnMsg::Files
+pub async fn spawn_file_cmd(
+    printer: PrinterConfig,
+    id: PrinterId,
+    cmd: FileCmd,
+    msg_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnMsg>,
+) {
+    match printer {
+        PrinterConfig::Bambu(_, printer) => {
+            let printer = printer.read().await.clone();
+            tokio::spawn(async move {
+                let result = run_bambu(&printer, cmd).await.map_err(|e| {
+                    warn!("file command failed on {:?}: {:?}", id, e);
+                    e.to_string()
+                });
+                let _ = msg_tx.send(PrinterConnMsg::Files(id, result));
+            });
+        }
+        PrinterConfig::Prusa(_, printer) => {
+            let printer = printer.read().await.clone();
+            tokio::spawn(async move {
+                let result = run_prusa(&printer, cmd).await.map_err(|e| {
+                    warn!("file command failed on {:?}: {:?}", id, e);
+                    e.to_string()
+                });
+                let _ = msg_tx.send(PrinterConnMsg::Files(id, result));
+            });
+        }
+        printer => {
+            let _ = msg_tx.send(PrinterConnMsg::Files(
+                id,
+                Err(format!(
+                    "file management not supported on {:?}",
+                    printer.printer_type()
+                )),
+            ));
+        }
+    }
+}
+
+/// read before connecting, so a bad path fails without waiting on the printer
+async fn read_upload(cmd: &FileCmd) -> Result<Option<(String, Vec<u8>)>> {
+    let FileCmd::Upload(local, _) = cmd else {
+        return Ok(None);
+    };
+    let name = local
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("bad file name")?
+        .to_string();
+    let data = tokio::fs::read(local)
+        .await
+        .with_context(|| format!("failed to read {:?}", local))?;
+    Ok(Some((name, data)))
+}
+
+/// MARK: Bambu
+/// implicit FTPS, the same on every current Bambu printer
+pub const FTPS_PORT: u16 = 990;
+
+/// over FTPS, one connection per command
+async fn run_bambu(printer: &PrinterConfigBambu, cmd: FileCmd) -> Result<FileResult> {
+    let upload = read_upload(&cmd).await?;
+
+    let mut ftps = BambuFtps::connect(&printer.host, FTPS_PORT, &printer.access_code).await?;
+
+    let out = match cmd {
+        FileCmd::List(dir) => {
+            let mut files = ftps.list_detailed(&dir).await?;
+            files.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.path.cmp(&b.path)));
+            FileResult::Listing(dir, files)
+        }
+        FileCmd::Upload(_, dir) => {
+            let (name, data) = upload.context("no upload")?;
+            let path = format!("{}/{}", dir.trim_end_matches('/'), name);
+            ftps.upload(&path, &data).await?;
+            FileResult::Uploaded(path)
+        }
+        FileCmd::Download(path, local) => {
+            let data = ftps.download(&path).await?;
+            tokio::fs::write(&local, data)
+                .await
+                .with_context(|| format!("failed to write {:?}", local))?;
+            FileResult::Downloaded(local)
+        }
+        FileCmd::Delete(path) => {
+            ftps.delete(&path).await?;
+            FileResult::Deleted(path)
+        }
+    };
+
+    if let Err(e) = ftps.quit().await {
+        debug!("ftps quit: {:?}", e);
+    }
+    Ok(out)
+}
+
+/// MARK: Prusa
+/// over the PrusaLink HTTP API
+async fn run_prusa(printer: &PrinterConfigPrusa, cmd: FileCmd) -> Result<FileResult> {
+    let upload = read_upload(&cmd).await?;
+    let files = PrusaFiles::new(reqwest::Client::new(), printer);
+
+    let out = match cmd {
+        FileCmd::List(dir) => {
+            let mut entries = files.list(&dir).await?;
+            entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.name().cmp(b.name())));
+            FileResult::Listing(dir, entries)
+        }
+        FileCmd::Upload(_, dir) => {
+            let (name, data) = upload.context("no upload")?;
+            FileResult::Uploaded(files.upload(&dir, &name, data).await?)
+        }
+        FileCmd::Download(path, local) => {
+            let data = files.download(&path).await?;
+            tokio::fs::write(&local, data)
+                .await
+                .with_context(|| format!("failed to write {:?}", local))?;
+            FileResult::Downloaded(local)
+        }
+        FileCmd::Delete(path) => {
+            files.delete(&path).await?;
+            FileResult::Deleted(path)
+        }
+    };
+    Ok(out)
+}
@@ -2,13 +2,16 @@ use anyhow::{anyhow, bail, ensure, Context, Result};
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    config::{printer_config::PrinterConfig, printer_id::PrinterId},
+    config::{printer_config::PrinterConfig, printer_id::PrinterId, AppConfig},
     status::GenericPrinterState,
 };
 
+use super::conn_klipper::klipper_types::metadata::KlipperMetadata;
+
 use super::worker_message::WorkerMsg;
 
 pub async fn spawn_fetch_thumbnail(
+    config: AppConfig,
     printer: PrinterConfig,
     state: GenericPrinterState,
     id: PrinterId,
@@ -17,9 +20,54 @@ pub async fn spawn_fetch_thumbnail(
     worker_msg_tx: tokio::sync::mpsc::UnboundedSender<(PrinterId, WorkerMsg)>,
 ) -> Result<()> {
     match printer {
-        PrinterConfig::Bambu(_, _) => todo!(),
-        PrinterConfig::Klipper(_, _) => todo!(),
-        // PrinterConfig::Octoprint(_, _) => todo!(),
+        PrinterConfig::Bambu(_, printer) => {
+            let printer = printer.read().await.clone();
+            let Some(state) = state.state_bambu else {
+                bail!("Printer state is not Bambu");
+            };
+
+            tokio::spawn(async move {
+                match super::conn_bambu::thumbnail::fetch(&config, &printer, &state).await {
+                    Ok(bytes) => {
+                        let _ = worker_msg_tx
+                            .send((id.clone(), WorkerMsg::FetchedThumbnail(id, file, bytes)));
+                    }
+                    Err(e) => warn!("failed to fetch thumbnail for {:?}: {:?}", file, e),
+                }
+            });
+
+            Ok(())
+        }
+        PrinterConfig::Klipper(_, printer) => {
+            let host = printer.read().await.host.clone();
+
+            tokio::spawn(async move {
+                match fetch_thumbnail_klipper(&host, &file).await {
+                    Ok(bytes) => {
+                        let _ = worker_msg_tx
+                            .send((id.clone(), WorkerMsg::FetchedThumbnail(id, file, bytes)));
+                    }
+                    Err(e) => warn!("failed to fetch thumbnail for {:?}: {:?}", file, e),
+                }
+            });
+
+            Ok(())
+        }
+        PrinterConfig::Octoprint(_, printer) => {
+            let client = super::conn_octoprint::OctoClientLocal::new(printer.read().await.clone())?;
+
+            tokio::spawn(async move {
+                match client.get_thumbnail(&file).await {
+                    Ok(bytes) => {
+                        let _ = worker_msg_tx
+                            .send((id.clone(), WorkerMsg::FetchedThumbnail(id, file, bytes)));
+                    }
+                    Err(e) => warn!("failed to fetch thumbnail for {:?}: {:?}", file, e),
+                }
+            });
+
+            Ok(())
+        }
         PrinterConfig::Prusa(_, printer) => {
             let printer = printer.read().await;
 
@@ -64,3 +112,42 @@ pub async fn spawn_fetch_thumbnail(
         }
     }
 }
+
+/// largest thumbnail listed in the file's `server.files.metadata`
+async fn fetch_thumbnail_klipper(host: &str, file: &str) -> Result<Vec<u8>> {
+    let client = reqwest::Client::new();
+    let base = format!("http://{}:7125/server/files", host);
+
+    let resp: serde_json::Value = client
+        .get(format!("{}/metadata", base))
+        .query(&[("filename", file)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let md: KlipperMetadata = serde_json::from_value(resp["result"].clone())?;
+
+    let Some(thumb) = md.thumbnails.iter().max_by_key(|t| t.width * t.height) else {
+        bail!("no thumbnails in {:?}", file);
+    };
+
+    /// relative to the directory of the G-code file
+    let path = match file.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, thumb.relative_path),
+        None => thumb.relative_path.clone(),
+    };
+    let mut url = reqwest::Url::parse(&format!("{}/gcodes", base))?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("bad url: {}", base))?
+        .extend(path.split('/'));
+
+    let bytes = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
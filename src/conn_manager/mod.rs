@@ -2,7 +2,10 @@ pub mod conn_bambu;
 pub mod conn_klipper;
 pub mod conn_octoprint;
 pub mod conn_prusa;
+pub mod files;
 pub mod helpers;
+pub mod motion;
+pub mod toolchanger;
 pub mod worker_message;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
@@ -12,43 +15,137 @@ use core::error;
 use std::{collections::HashMap, sync::Arc};
 
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use worker_message::WorkerMsg;
 
 use crate::{
     config::{printer_config::PrinterConfig, printer_id::PrinterId, AppConfig},
+    error_logging::error_db::{ErrorEntry, ErrorPage, ErrorQuery, ExportFormat, JobEndState},
     status::{GenericPrinterState, PrinterState},
     streaming::StreamCmd,
 };
-use conn_bambu::{errors::ErrorMap, message::Message};
+use conn_bambu::{errors::ErrorMap, hms::HmsMessage, message::Message};
 
 /// messages from PrinterConnManager to UI
 #[derive(Debug, Clone)]
 pub enum PrinterConnMsg {
     WorkerMsg(PrinterId, WorkerMsg),
     NewThumbnail(PrinterId, String, Vec<u8>),
+    /// outcome of a PrinterConnCmd::WorkerCmd
+    CommandResult(PrinterId, CommandId, CommandResult),
+    /// reply to PrinterConnCmd::QueryEvents
+    Events(std::result::Result<ErrorPage, String>),
+    /// reply to PrinterConnCmd::ExportEvents, number of entries written
+    EventsExported(std::path::PathBuf, std::result::Result<usize, String>),
+    /// reply to PrinterConnCmd::Files
+    Files(PrinterId, std::result::Result<files::FileResult, String>),
 }
 
 /// messages from UI to PrinterConnManager
 #[derive(Debug, Clone)]
 pub enum PrinterConnCmd {
     FetchThumbnail(PrinterId, String),
+    /// forwarded to the worker task for that printer,
+    /// answered with a PrinterConnMsg::CommandResult with the same id
+    WorkerCmd(PrinterId, CommandId, WorkerCmd),
+    /// search the error/event log
+    QueryEvents(ErrorQuery),
+    /// write every match to a file, ignoring paging
+    ExportEvents(ErrorQuery, ExportFormat, std::path::PathBuf),
+    /// (wake, bed) hours from Options, for notification quiet hours
+    SetQuietHours(u32, u32),
+    /// list, upload, download or delete files on the printer's storage
+    Files(PrinterId, files::FileCmd),
     //
 }
 
-#[derive(Debug)]
+/// commands from PrinterConnManager to worker tasks,
+/// each worker translates these into its own protocol.
+/// as JSON: `{"cmd": "set_bed_temp", "args": 60.0}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", content = "args", rename_all = "snake_case")]
 pub enum WorkerCmd {
-    //
+    Pause,
+    Resume,
+    Cancel,
+    /// tool index (None = current/only extruder), target temp
+    SetNozzleTemp(Option<usize>, f32),
+    SetBedTemp(f32),
+    /// part cooling fan, 0.0 - 1.0
+    SetFan(f32),
+    SetLight(bool),
+    /// raw G-code, may contain multiple lines
+    Gcode(String),
+    /// Bambu only
+    SetAmsFilament(conn_bambu::command::ChangeAMSFilamentSetting),
+    /// prints a file already on the printer, by its path from PrinterConnCmd::Files
+    PrintFile(String),
+    /// Bambu only, prints a .3mf from the SD card with plate and AMS options
+    PrintProjectFile(conn_bambu::command::ProjectFile),
+    /// Klipper only, shuts down the MCU immediately
+    EmergencyStop,
+    /// Klipper only, e.g. to recover after an emergency stop
+    FirmwareRestart,
+    /// Prusa (through OctoPrint) and Klipper with KTCC
+    Toolchanger(toolchanger::ToolchangerCmd),
+    /// jog and home, refused while printing
+    Motion(motion::MotionCmd),
+}
+
+/// for matching a command with its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandId(u64);
+
+impl CommandId {
+    pub fn next() -> Self {
+        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    }
+
+    pub fn inner(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandResult {
+    Acked,
+    Rejected(String),
+    TimedOut,
+}
+
+/// print job currently being recorded in the print_jobs table
+#[derive(Debug, Clone)]
+struct ActiveJob {
+    job_id: i64,
+    /// a recoverable error happened, so going idle means the job failed
+    had_error: bool,
 }
 
+/// how long to wait for a worker to report a result before giving up
+pub(crate) const COMMAND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
 pub struct PrinterConnManager {
     config: AppConfig,
 
     error_db: crate::error_logging::error_db::ErrorDb,
+    notify: crate::notifications::NotifyRouter,
+    alert_rules: crate::notifications::rules::AlertRules,
+    /// last status update from each printer, for the disconnected rule
+    last_seen: HashMap<PrinterId, std::time::Instant>,
+    /// printers already checked for finishing during quiet hours this print
+    eta_checked: std::collections::HashSet<PrinterId>,
 
     // printers: HashMap<PrinterId, BambuClient>,
     printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
-    worker_cmd_txs: HashMap<PrinterId, tokio::sync::mpsc::UnboundedSender<WorkerCmd>>,
+    worker_cmd_txs: HashMap<PrinterId, tokio::sync::mpsc::UnboundedSender<(CommandId, WorkerCmd)>>,
+    /// commands sent to workers that haven't reported a result yet
+    pending_cmds: HashMap<CommandId, (PrinterId, std::time::Instant)>,
+    cmd_timeout_timer: tokio::time::Interval,
+    active_jobs: HashMap<PrinterId, ActiveJob>,
+    /// time of the last sample written to the database
+    persisted_samples: HashMap<PrinterId, f64>,
 
     cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
     cmd_rx: tokio::sync::mpsc::UnboundedReceiver<PrinterConnCmd>,
@@ -93,15 +190,27 @@ impl PrinterConnManager {
             .await
             .unwrap();
 
+        let notify = crate::notifications::NotifyRouter::new(config.notify());
+        let alert_rules =
+            crate::notifications::rules::AlertRules::new(config.notify().rules.clone());
+
         Self {
             config,
 
             error_db,
+            notify,
+            alert_rules,
+            last_seen: HashMap::new(),
+            eta_checked: std::collections::HashSet::new(),
             // printer_states,
 
             // printers: HashMap::new(),
             printer_states,
             worker_cmd_txs: HashMap::new(),
+            pending_cmds: HashMap::new(),
+            cmd_timeout_timer: tokio::time::interval(std::time::Duration::from_secs(1)),
+            active_jobs: HashMap::new(),
+            persisted_samples: HashMap::new(),
             cmd_tx,
             cmd_rx,
             msg_tx,
@@ -118,6 +227,12 @@ impl PrinterConnManager {
     }
 
     pub async fn init(&mut self) -> Result<()> {
+        if self.config.history().persist {
+            if let Err(e) = self.restore_history().await {
+                error!("failed to restore temp history: {:?}", e);
+            }
+        }
+
         for printer in self.config.printers() {
             debug!("adding printer");
             self.add_printer(printer).await?;
@@ -139,6 +254,12 @@ impl PrinterConnManager {
                     self.handle_printer_msg(id, printer_msg).await?;
                     // panic!("TODO: handle printer message");
                 }
+                _ = self.cmd_timeout_timer.tick() => {
+                    self.check_command_timeouts()?;
+                    self.check_alert_rules().await;
+                    self.check_eta_overnight().await;
+                    self.notify.send_held();
+                }
             }
         }
     }
@@ -157,7 +278,8 @@ impl PrinterConnManager {
         }
         self.kill_chans.insert(id.clone(), kill_tx);
 
-        let (worker_cmd_tx, worker_cmd_rx) = tokio::sync::mpsc::unbounded_channel::<WorkerCmd>();
+        let (worker_cmd_tx, worker_cmd_rx) =
+            tokio::sync::mpsc::unbounded_channel::<(CommandId, WorkerCmd)>();
 
         match printer {
             PrinterConfig::Bambu(_, printer) => {
@@ -171,6 +293,11 @@ impl PrinterConnManager {
                 .await?;
 
                 self.worker_cmd_txs.insert(id.clone(), worker_cmd_tx);
+                tokio::task::spawn(async move {
+                    if let Err(e) = client.run().await {
+                        error!("error running bambu client: {:?}", e);
+                    }
+                });
             }
             PrinterConfig::Klipper(_, printer) => {
                 self.worker_cmd_txs.insert(id.clone(), worker_cmd_tx);
@@ -217,9 +344,25 @@ impl PrinterConnManager {
                         }
                     }
                 });
-            } // PrinterConfig::Octoprint(_, printer) => {
-              //     todo!();
-              // }
+            }
+            PrinterConfig::Octoprint(_, printer) => {
+                let mut client = conn_octoprint::octo_worker::OctoWorker::new(
+                    printer.clone(),
+                    self.worker_msg_tx.clone(),
+                    worker_cmd_rx,
+                    kill_rx,
+                    None,
+                )
+                .await?;
+                self.worker_cmd_txs.insert(id.clone(), worker_cmd_tx);
+                tokio::task::spawn(async move {
+                    loop {
+                        if let Err(e) = client.run().await {
+                            error!("error running octoprint client: {:?}", e);
+                        }
+                    }
+                });
+            }
         }
 
         Ok(())
@@ -233,6 +376,15 @@ impl PrinterConnManager {
             bail!("printer not found: {:?}", id);
         };
 
+        if matches!(
+            msg,
+            WorkerMsg::StatusUpdate(_)
+                | WorkerMsg::StatusUpdatePrusa(_)
+                | WorkerMsg::StatusUpdateBambu(_)
+        ) {
+            self.last_seen.insert(id.clone(), std::time::Instant::now());
+        }
+
         match msg {
             WorkerMsg::StatusUpdate(update) => {
                 // debug!("conn manager got status update: {:?}", id);
@@ -260,6 +412,7 @@ impl PrinterConnManager {
 
                             crate::notifications::alert_printer_error(
                                 &self.error_db,
+                                &self.notify,
                                 &printer.id(),
                                 &printer.name().await,
                                 e,
@@ -269,6 +422,7 @@ impl PrinterConnManager {
                         } else {
                             crate::notifications::alert_printer_error(
                                 &self.error_db,
+                                &self.notify,
                                 &printer.id(),
                                 &printer.name().await,
                                 -1,
@@ -292,6 +446,7 @@ impl PrinterConnManager {
                         warn!("sent finish notification");
                         crate::notifications::alert_print_complete(
                             &self.error_db,
+                            &self.notify,
                             &printer.id(),
                             &printer.name().await,
                             state
@@ -303,6 +458,12 @@ impl PrinterConnManager {
                     }
                 }
 
+                let state2 = state.clone();
+                drop(state);
+                if prev_state != state2.state {
+                    self.track_print_job(&id, &state2).await;
+                }
+
                 // self.msg_tx.send(PrinterConnMsg::WorkerMsg(id, msg))?;
             }
             WorkerMsg::StatusUpdatePrusa(update) => {
@@ -313,13 +474,26 @@ impl PrinterConnManager {
 
                 // self.msg_tx.send(PrinterConnMsg::WorkerMsg(id, msg))?;
             }
-            WorkerMsg::StatusUpdateBambu(update) => {
+            WorkerMsg::StatusUpdateBambu(mut update) => {
                 // debug!("updating bambu state");
+                for h in update.hms.iter_mut() {
+                    h.text = self
+                        .error_map
+                        .get_hms(h.entry.ecode())
+                        .map(|s| s.to_string());
+                }
+
                 let mut state = self.printer_states.entry(id.clone()).or_default();
                 let bt = state
                     .state_bambu
                     .as_ref()
                     .and_then(|bs| bs.printer_type.clone());
+                let prev_hms = state
+                    .state_bambu
+                    .as_ref()
+                    .map(|bs| bs.hms.clone())
+                    .unwrap_or_default();
+                let hms = update.hms.clone();
                 state.update_bambu(update);
                 if let Some(bt) = bt {
                     state
@@ -327,6 +501,9 @@ impl PrinterConnManager {
                         .as_mut()
                         .map(|bs| bs.printer_type = Some(bt));
                 }
+                drop(state);
+
+                self.log_hms_changes(&id, &prev_hms, &hms).await;
             }
 
             WorkerMsg::SetBambuType(t) => {
@@ -347,12 +524,33 @@ impl PrinterConnManager {
                     .send(PrinterConnMsg::NewThumbnail(id, file, img))?;
             }
 
+            WorkerMsg::CommandResult(cmd_id, result) => {
+                if self.pending_cmds.remove(&cmd_id).is_none() {
+                    debug!("result for unknown or timed out command: {:?}", cmd_id);
+                    return Ok(());
+                }
+                if let CommandResult::Rejected(e) = &result {
+                    warn!("command rejected: {:?}, {}", cmd_id, e);
+                }
+                self.msg_tx
+                    .send(PrinterConnMsg::CommandResult(id.clone(), cmd_id, result))?;
+            }
+
+            WorkerMsg::Terminal(lines) => {
+                let state = self.printer_states.entry(id.clone()).or_default();
+                state.terminal.extend(lines);
+            }
+
             WorkerMsg::Connecting => {}
             WorkerMsg::Connected => {}
             WorkerMsg::Reconnecting => {}
             WorkerMsg::Disconnected => {}
         }
 
+        if self.config.history().persist {
+            self.persist_history(&id).await;
+        }
+
         Ok(())
     }
 
@@ -366,6 +564,7 @@ impl PrinterConnManager {
                     bail!("printer state not found: {:?}", id);
                 };
                 helpers::spawn_fetch_thumbnail(
+                    self.config.clone(),
                     printer,
                     state.clone(),
                     id,
@@ -374,6 +573,388 @@ impl PrinterConnManager {
                 )
                 .await?;
             }
+            PrinterConnCmd::WorkerCmd(id, cmd_id, cmd) => {
+                let Some(tx) = self.worker_cmd_txs.get(&id) else {
+                    self.msg_tx.send(PrinterConnMsg::CommandResult(
+                        id.clone(),
+                        cmd_id,
+                        CommandResult::Rejected("printer not connected".to_string()),
+                    ))?;
+                    /// not fatal, the HTTP API and MQTT bridge can target any printer
+                    warn!("printer worker not found: {:?}", id);
+                    return Ok(());
+                };
+                if let Some(e) = self.check_command(&id, &cmd) {
+                    self.msg_tx.send(PrinterConnMsg::CommandResult(
+                        id,
+                        cmd_id,
+                        CommandResult::Rejected(e),
+                    ))?;
+                    return Ok(());
+                }
+                debug!("sending worker cmd: {:?} = {:?}", id, cmd);
+                if let Err(e) = tx.send((cmd_id, cmd)) {
+                    error!("worker channel closed: {:?}, {:?}", id, e);
+                    self.msg_tx.send(PrinterConnMsg::CommandResult(
+                        id,
+                        cmd_id,
+                        CommandResult::Rejected("printer worker stopped".to_string()),
+                    ))?;
+                } else {
+                    self.pending_cmds
+                        .insert(cmd_id, (id, std::time::Instant::now()));
+                }
+            }
+            PrinterConnCmd::QueryEvents(query) => {
+                let page = self.query_events(query).await.map_err(|e| e.to_string());
+                self.msg_tx.send(PrinterConnMsg::Events(page))?;
+            }
+            PrinterConnCmd::Files(id, cmd) => {
+                let Some(printer) = self.config.get_printer(&id) else {
+                    bail!("printer not found: {:?}", id);
+                };
+                files::spawn_file_cmd(printer, id, cmd, self.msg_tx.clone()).await;
+            }
+            PrinterConnCmd::SetQuietHours(wake, bed) => {
+                debug!("quiet hours: {}:00 - {}:00", bed, wake);
+                self.notify.quiet_hours().lock().set_hours(wake, bed);
+            }
+            PrinterConnCmd::ExportEvents(query, format, path) => {
+                let query = ErrorQuery {
+                    limit: -1,
+                    offset: 0,
+                    ..query
+                };
+                let result = self
+                    .export_events(&query, format, &path)
+                    .await
+                    .map_err(|e| e.to_string());
+                self.msg_tx
+                    .send(PrinterConnMsg::EventsExported(path, result))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// HMS messages are written when they appear and when they clear
+    async fn log_hms_changes(&self, id: &PrinterId, prev: &[HmsMessage], hms: &[HmsMessage]) {
+        let appeared = hms
+            .iter()
+            .filter(|h| !prev.iter().any(|p| p.entry == h.entry))
+            .map(|h| (h, "appeared"));
+        let cleared = prev
+            .iter()
+            .filter(|p| !hms.iter().any(|h| h.entry == p.entry))
+            .map(|h| (h, "cleared"));
+
+        for (h, change) in appeared.chain(cleared) {
+            let msg = format!(
+                "HMS {} {} ({}, {}): {}",
+                h.entry.code_str(),
+                change,
+                h.entry.severity().to_text(),
+                h.entry.module(),
+                h.text(),
+            );
+            info!("{:?}: {}", id, msg);
+            if let Err(e) = self.error_db.insert(id.inner(), &msg).await {
+                error!("failed to log HMS message: {:?}", e);
+            }
+        }
+    }
+
+    /// commands that would ruin a running print or that the printer can't run, returns why
+    fn check_command(&self, id: &PrinterId, cmd: &WorkerCmd) -> Option<String> {
+        match cmd {
+            WorkerCmd::Motion(motion) => {
+                if motion.is_empty_jog() {
+                    return Some("jog without a distance".to_string());
+                }
+                self.printer_states.get(id)?.motion_blocked()
+            }
+            /// one nozzle temperature per tool, same as the tool menus
+            WorkerCmd::Toolchanger(cmd) => {
+                let tool = cmd.tool()?;
+                let tools = self.printer_states.get(id)?.nozzle_temps.len().max(1);
+                (tool >= tools)
+                    .then(|| format!("unknown tool: {}, the printer has {}", tool, tools))
+            }
+            _ => None,
+        }
+    }
+
+    /// MARK: event log
+    async fn query_events(&self, query: ErrorQuery) -> Result<ErrorPage> {
+        let mut entries = self.error_db.query(&query).await?;
+        let total = self.error_db.count(&query).await?;
+        self.decode_errors(&mut entries);
+        Ok(ErrorPage {
+            query,
+            entries,
+            total,
+        })
+    }
+
+    async fn export_events(
+        &self,
+        query: &ErrorQuery,
+        format: ExportFormat,
+        path: &std::path::Path,
+    ) -> Result<usize> {
+        let mut entries = self.error_db.query(query).await?;
+        self.decode_errors(&mut entries);
+
+        let file =
+            std::fs::File::create(path).with_context(|| format!("failed to create {:?}", path))?;
+        format.write(&entries, std::io::BufWriter::new(file))?;
+
+        debug!("exported {} events to {:?}", entries.len(), path);
+        Ok(entries.len())
+    }
+
+    fn decode_errors(&self, entries: &mut [ErrorEntry]) {
+        for e in entries.iter_mut() {
+            e.description = e
+                .code()
+                .and_then(|c| self.error_map.get_error(c as u64))
+                .map(|s| s.to_string());
+        }
+    }
+
+    /// MARK: temp history
+    async fn restore_history(&mut self) -> Result<()> {
+        let retention = self.config.history().retention_days as f64 * 24. * 60. * 60.;
+        let now = chrono::Utc::now().timestamp() as f64;
+        let pruned = self.error_db.prune_samples(now - retention).await?;
+        debug!("pruned {} old temp samples", pruned);
+
+        for id in self.config.printer_ids_async().await {
+            let samples = self
+                .error_db
+                .load_samples(id.inner(), crate::status::history::History::CAPACITY)
+                .await?;
+            if let Some(last) = samples.last() {
+                self.persisted_samples.insert(id.clone(), last.time);
+            }
+            self.printer_states
+                .entry(id)
+                .or_default()
+                .history
+                .extend(samples);
+        }
+        Ok(())
+    }
+
+    /// writes the newest sample if it hasn't been written yet
+    async fn persist_history(&mut self, id: &PrinterId) {
+        let Some(sample) = self
+            .printer_states
+            .get(id)
+            .and_then(|state| state.history.last())
+        else {
+            return;
+        };
+        let last = self.persisted_samples.get(id).copied().unwrap_or(0.);
+        if sample.time <= last {
+            return;
+        }
+        self.persisted_samples.insert(id.clone(), sample.time);
+        if let Err(e) = self.error_db.insert_sample(id.inner(), &sample).await {
+            error!("failed to persist temp sample: {:?}", e);
+        }
+    }
+
+    /// MARK: job history
+    /// called on state transitions, opens and closes rows in print_jobs
+    async fn track_print_job(&mut self, id: &PrinterId, state: &GenericPrinterState) {
+        let end = match &state.state {
+            PrinterState::Printing | PrinterState::Paused => {
+                if self.active_jobs.contains_key(id) {
+                    return;
+                }
+                let bambu = state.state_bambu.as_ref();
+                match self
+                    .error_db
+                    .job_started(
+                        id.inner(),
+                        state.current_file.as_deref(),
+                        bambu.and_then(|b| b.subtask_id.as_deref()),
+                        bambu.and_then(|b| b.task_id.as_deref()),
+                    )
+                    .await
+                {
+                    Ok(job_id) => {
+                        debug!("print job started: {:?}, {}", id, job_id);
+                        self.set_job_active(id, true);
+                        self.active_jobs.insert(
+                            id.clone(),
+                            ActiveJob {
+                                job_id,
+                                had_error: false,
+                            },
+                        );
+                    }
+                    Err(e) => error!("failed to record print job: {:?}", e),
+                }
+                return;
+            }
+            PrinterState::Error(e) => {
+                let Some(job) = self.active_jobs.get_mut(id) else {
+                    return;
+                };
+                let bambu_cancelled = state
+                    .state_bambu
+                    .as_ref()
+                    .and_then(|b| b.print_error.as_ref())
+                    .is_some_and(|e| e.is_cancelled());
+                match e.as_deref() {
+                    Some("Cancelled") => JobEndState::Cancelled,
+                    Some("Failed") if bambu_cancelled => JobEndState::Cancelled,
+                    Some("Failed") => JobEndState::Failed,
+                    /// may still be resumed
+                    _ => {
+                        job.had_error = true;
+                        return;
+                    }
+                }
+            }
+            PrinterState::Finished => JobEndState::Finished,
+            PrinterState::Idle => {
+                let Some(job) = self.active_jobs.get(id) else {
+                    return;
+                };
+                /// prusa reports both FINISHED and STOPPED as idle
+                let stopped = state
+                    .state_prusa
+                    .as_ref()
+                    .is_some_and(|p| p.status.printer.state == "STOPPED");
+                if stopped {
+                    JobEndState::Cancelled
+                } else if job.had_error {
+                    JobEndState::Failed
+                } else {
+                    JobEndState::Finished
+                }
+            }
+            /// disconnects don't end a job
+            _ => return,
+        };
+
+        let Some(job) = self.active_jobs.remove(id) else {
+            return;
+        };
+        self.set_job_active(id, false);
+        debug!("print job ended: {:?}, {}, {:?}", id, job.job_id, end);
+        let layers = state.layer.map(|(_, total)| total);
+        if let Err(e) = self.error_db.job_ended(job.job_id, end, layers).await {
+            error!("failed to record print job end: {:?}", e);
+        }
+    }
+
+    /// mirrors `active_jobs` into the state, so the UI refuses motion the same way
+    fn set_job_active(&self, id: &PrinterId, active: bool) {
+        if let Some(mut state) = self.printer_states.get_mut(id) {
+            state.job_active = active;
+        }
+    }
+
+    /// MARK: alert rules
+    async fn check_alert_rules(&mut self) {
+        if self.alert_rules.is_empty() {
+            return;
+        }
+
+        let now = std::time::Instant::now();
+        let mut alerts = vec![];
+        for printer in self.config.printers() {
+            let id = printer.id();
+            let name = printer.name().await;
+            let state = self.printer_states.get(&id);
+            alerts.extend(self.alert_rules.check(
+                &id,
+                &name,
+                state.as_deref(),
+                self.last_seen.get(&id).copied(),
+                now,
+            ));
+        }
+
+        for n in alerts {
+            info!("alert rule fired: {:?} {:?}", n.event, n.title);
+            crate::notifications::alert_rule(&self.error_db, &self.notify, n).await;
+        }
+    }
+
+    /// once per print, warn if it's going to finish during quiet hours
+    async fn check_eta_overnight(&mut self) {
+        if !self.notify.quiet_hours().lock().eta_warning() {
+            return;
+        }
+
+        let now = chrono::Local::now();
+        /// too late to warn, it would just be held until morning
+        let quiet_now = self.notify.quiet_hours().lock().is_quiet_at(now);
+        let mut finishing = vec![];
+        for entry in self.printer_states.iter() {
+            let (id, state) = (entry.key(), entry.value());
+            if !matches!(state.state, PrinterState::Printing | PrinterState::Paused) {
+                self.eta_checked.remove(id);
+                continue;
+            }
+            if self.eta_checked.contains(id) {
+                continue;
+            }
+
+            let eta = state.state_bambu.as_ref().and_then(|b| b.eta).or_else(|| {
+                state
+                    .time_remaining
+                    .filter(|t| t.num_seconds() > 0)
+                    .map(|t| now + t)
+            });
+            let Some(eta) = eta else {
+                continue;
+            };
+
+            self.eta_checked.insert(id.clone());
+            if !quiet_now && self.notify.quiet_hours().lock().is_quiet_at(eta) {
+                finishing.push((id.clone(), eta, state.current_file.clone()));
+            }
+        }
+
+        for (id, eta, file) in finishing {
+            let Some(printer) = self.config.get_printer(&id) else {
+                continue;
+            };
+            let name = printer.name().await;
+            let n = crate::notifications::Notification::new(
+                crate::notifications::NotifyEvent::EtaOvernight,
+                &id,
+                &name,
+                format!("{} will finish during quiet hours", name),
+                format!("ETA {}, {}", eta.format("%H:%M"), file.unwrap_or_default()),
+            );
+            crate::notifications::alert_rule(&self.error_db, &self.notify, n).await;
+        }
+    }
+
+    fn check_command_timeouts(&mut self) -> Result<()> {
+        let now = std::time::Instant::now();
+        let expired = self
+            .pending_cmds
+            .iter()
+            .filter(|(_, (_, sent))| now.duration_since(*sent) > COMMAND_TIMEOUT)
+            .map(|(cmd_id, _)| *cmd_id)
+            .collect::<Vec<_>>();
+
+        for cmd_id in expired {
+            if let Some((id, _)) = self.pending_cmds.remove(&cmd_id) {
+                warn!("command timed out: {:?}, {:?}", id, cmd_id);
+                self.msg_tx.send(PrinterConnMsg::CommandResult(
+                    id,
+                    cmd_id,
+                    CommandResult::TimedOut,
+                ))?;
+            }
         }
         Ok(())
     }
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use serde::{Deserialize, Serialize};
+
+/// jogging and homing, refused by the manager while a print is running.
+/// as JSON: `{"cmd": "motion", "args": {"op": "jog", "x": 10.0}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum MotionCmd {
+    /// relative move in mm
+    Jog {
+        #[serde(default)]
+        x: Option<f64>,
+        #[serde(default)]
+        y: Option<f64>,
+        #[serde(default)]
+        z: Option<f64>,
+        /// mm/min, defaults to JOG_SPEED_XY or JOG_SPEED_Z
+        #[serde(default)]
+        speed: Option<u32>,
+    },
+    /// homes every axis if none are set
+    Home {
+        #[serde(default)]
+        x: bool,
+        #[serde(default)]
+        y: bool,
+        #[serde(default)]
+        z: bool,
+    },
+}
+
+impl MotionCmd {
+    pub const JOG_SPEED_XY: u32 = 3000;
+    pub const JOG_SPEED_Z: u32 = 600;
+
+    pub fn jog_x(dist: f64) -> Self {
+        Self::Jog {
+            x: Some(dist),
+            y: None,
+            z: None,
+            speed: None,
+        }
+    }
+
+    pub fn jog_y(dist: f64) -> Self {
+        Self::Jog {
+            x: None,
+            y: Some(dist),
+            z: None,
+            speed: None,
+        }
+    }
+
+    pub fn jog_z(dist: f64) -> Self {
+        Self::Jog {
+            x: None,
+            y: None,
+            z: Some(dist),
+            speed: None,
+        }
+    }
+
+    pub fn home_all() -> Self {
+        Self::Home {
+            x: false,
+            y: false,
+            z: false,
+        }
+    }
+
+    /// a jog without any axis, which would only set the feedrate
+    pub fn is_empty_jog(&self) -> bool {
+        matches!(
+            self,
+            Self::Jog {
+                x: None,
+                y: None,
+                z: None,
+                ..
+            }
+        )
+    }
+
+    /// Z is slower on most printers, so a move with only Z uses its own speed
+    pub fn jog_speed(&self) -> u32 {
+        match self {
+            Self::Jog {
+                x: None,
+                y: None,
+                speed,
+                ..
+            } => speed.unwrap_or(Self::JOG_SPEED_Z),
+            Self::Jog { speed, .. } => speed.unwrap_or(Self::JOG_SPEED_XY),
+            Self::Home { .. } => 0,
+        }
+    }
+
+    /// plain G-code, for printers without their own motion API
+    pub fn to_gcode(&self) -> Vec<String> {
+        match self {
+            Self::Jog { x, y, z, .. } => {
+                let mut g1 = "G1".to_string();
+                for (axis, dist) in [("X", x), ("Y", y), ("Z", z)] {
+                    if let Some(dist) = dist {
+                        g1.push_str(&format!(" {}{:.3}", axis, dist));
+                    }
+                }
+                g1.push_str(&format!(" F{}", self.jog_speed()));
+
+                /// back to absolute positioning, which sliced G-code expects
+                vec!["G91".to_string(), g1, "G90".to_string()]
+            }
+            Self::Home { x, y, z } => {
+                let mut g28 = "G28".to_string();
+                for (axis, home) in [("X", x), ("Y", y), ("Z", z)] {
+                    if *home {
+                        g28.push_str(&format!(" {}", axis));
+                    }
+                }
+                vec![g28]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::{GenericPrinterState, PrinterState};
+
+    #[test]
+    fn empty_jog() {
+        let empty = MotionCmd::Jog {
+            x: None,
+            y: None,
+            z: None,
+            speed: Some(1000),
+        };
+        assert!(empty.is_empty_jog());
+        assert!(!MotionCmd::jog_z(1.).is_empty_jog());
+        assert!(!MotionCmd::home_all().is_empty_jog());
+    }
+
+    #[test]
+    fn blocked_mid_print() {
+        let state = |state: PrinterState, job_active: bool| GenericPrinterState {
+            state,
+            job_active,
+            ..Default::default()
+        };
+
+        assert!(state(PrinterState::Idle, false).motion_blocked().is_none());
+        assert!(state(PrinterState::Busy, false).motion_blocked().is_some());
+        assert!(state(PrinterState::Paused, false)
+            .motion_blocked()
+            .is_some());
+
+        /// an error only blocks motion while a job is open
+        let error = PrinterState::Error(Some("Failed".to_string()));
+        assert!(state(error.clone(), false).motion_blocked().is_none());
+        assert!(state(error, true).motion_blocked().is_some());
+        assert!(state(PrinterState::Unknown("PAUSE".to_string()), true)
+            .motion_blocked()
+            .is_some());
+    }
+}
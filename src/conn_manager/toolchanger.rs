@@ -0,0 +1,152 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use serde::{Deserialize, Serialize};
+
+/// toolchanger operations, each worker translates these into its own G-code.
+/// as JSON: `{"cmd": "toolchanger", "args": {"op": "load_filament", "tool": 2, "filament": "PETG", "temp": 245.0}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ToolchangerCmd {
+    PickupTool {
+        tool: usize,
+    },
+    /// parks whichever tool is picked up
+    ParkTool,
+    /// heats the tool first if `temp` is set, otherwise the printer's own preset is used
+    LoadFilament {
+        tool: usize,
+        filament: FilamentType,
+        #[serde(default)]
+        temp: Option<f32>,
+    },
+    UnloadFilament {
+        tool: usize,
+    },
+    /// mm, relative to the first tool
+    SetOffset {
+        tool: usize,
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+}
+
+impl ToolchangerCmd {
+    pub fn tool(&self) -> Option<usize> {
+        match self {
+            Self::PickupTool { tool }
+            | Self::LoadFilament { tool, .. }
+            | Self::UnloadFilament { tool }
+            | Self::SetOffset { tool, .. } => Some(*tool),
+            Self::ParkTool => None,
+        }
+    }
+
+    pub fn load(tool: usize, filament: FilamentType) -> Self {
+        let temp = filament.default_temp();
+        Self::LoadFilament {
+            tool,
+            filament,
+            temp,
+        }
+    }
+}
+
+/// MARK: FilamentType
+/// a material name as the printer knows it, e.g. "PLA" or "PETG".
+/// ends up inside `M701 S"..."` and `LOAD_FILAMENT MATERIAL=...`, so only
+/// letters, digits and `-_+.` are allowed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FilamentType(String);
+
+impl FilamentType {
+    /// (name, nozzle temperature) for the load menus
+    pub const PRESETS: &'static [(&'static str, f32)] = &[
+        ("PLA", 215.),
+        ("PETG", 240.),
+        ("ASA", 260.),
+        ("ABS", 255.),
+        ("PC", 275.),
+        ("TPU", 230.),
+        ("PA", 285.),
+    ];
+
+    pub fn new(name: &str) -> Result<Self> {
+        let name = name.trim();
+        ensure!(!name.is_empty(), "empty filament type");
+        if let Some(c) = name
+            .chars()
+            .find(|c| !c.is_ascii_alphanumeric() && !"-_+.".contains(*c))
+        {
+            bail!("invalid character in filament type {:?}: {:?}", name, c);
+        }
+        Ok(Self(name.to_string()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn default_temp(&self) -> Option<f32> {
+        Self::PRESETS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&self.0))
+            .map(|(_, temp)| *temp)
+    }
+
+    pub fn presets() -> impl Iterator<Item = Self> {
+        Self::PRESETS.iter().map(|(name, _)| Self(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for FilamentType {
+    type Error = anyhow::Error;
+
+    fn try_from(name: String) -> Result<Self> {
+        Self::new(&name)
+    }
+}
+
+impl From<FilamentType> for String {
+    fn from(filament: FilamentType) -> Self {
+        filament.0
+    }
+}
+
+impl std::fmt::Display for FilamentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filament_type_names() {
+        assert_eq!(FilamentType::new(" PETG ").unwrap().name(), "PETG");
+        assert!(FilamentType::new("PA12-CF").is_ok());
+
+        for bad in ["", "PLA\"", "PLA Silk", "PLA\nG28", "PLA;"] {
+            assert!(FilamentType::new(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn filament_type_json() {
+        let cmd: ToolchangerCmd = serde_json::from_str(
+            r#"{"op": "load_filament", "tool": 1, "filament": "PETG", "temp": 240.0}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cmd,
+            ToolchangerCmd::load(1, FilamentType::new("PETG").unwrap())
+        );
+
+        let bad = r#"{"op": "load_filament", "tool": 1, "filament": "PLA\"\nM112"}"#;
+        assert!(serde_json::from_str::<ToolchangerCmd>(bad).is_err());
+    }
+}
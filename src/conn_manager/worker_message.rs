@@ -13,6 +13,10 @@ pub enum WorkerMsg {
     StatusUpdateBambu(crate::status::bambu_status::PrinterStateBambu),
     SetBambuType(crate::status::bambu_status::BambuPrinterType),
     FetchedThumbnail(PrinterId, String, Vec<u8>),
+    /// result of a WorkerCmd
+    CommandResult(super::CommandId, super::CommandResult),
+    /// new serial log lines
+    Terminal(Vec<crate::status::terminal::TerminalLine>),
 
     Connecting,
     Connected,
@@ -1,18 +1,132 @@
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use tracing::{debug, error, info, trace, warn};
 
-use sqlx::{Connection, SqliteConnection, SqlitePool};
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+
+use crate::status::history::Sample;
 
 pub struct ErrorDb {
     // db: SqliteConnection,
     db: sqlx::Pool<sqlx::Sqlite>,
 }
 
-#[derive(Debug)]
+/// how a print job ended, stored as text in print_jobs.final_state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobEndState {
+    Finished,
+    Cancelled,
+    Failed,
+    /// still open when a different job started on the same printer
+    Aborted,
+}
+
+impl JobEndState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobEndState::Finished => "finished",
+            JobEndState::Cancelled => "cancelled",
+            JobEndState::Failed => "failed",
+            JobEndState::Aborted => "aborted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct ErrorEntry {
     pub id: i64,
     pub printer_id: String,
+    /// UTC
+    pub timestamp: chrono::NaiveDateTime,
     pub message: String,
+    /// filled in from the ErrorMap when queried
+    #[sqlx(skip)]
+    pub description: Option<String>,
+}
+
+impl ErrorEntry {
+    /// code from messages written by `alert_printer_error`
+    pub fn code(&self) -> Option<i64> {
+        let rest = self.message.strip_prefix("error (code ")?;
+        rest.split_once(')')?.0.parse().ok()
+    }
+}
+
+/// filters for `ErrorDb::query`, None matches everything
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorQuery {
+    pub printer_id: Option<String>,
+    /// UTC
+    pub since: Option<chrono::NaiveDateTime>,
+    /// UTC
+    pub until: Option<chrono::NaiveDateTime>,
+    /// substring of the message
+    pub text: Option<String>,
+    /// -1 for no limit
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for ErrorQuery {
+    fn default() -> Self {
+        Self {
+            printer_id: None,
+            since: None,
+            until: None,
+            text: None,
+            limit: 100,
+            offset: 0,
+        }
+    }
+}
+
+/// one page of results, with the total number of matches
+#[derive(Debug, Clone)]
+pub struct ErrorPage {
+    pub query: ErrorQuery,
+    pub entries: Vec<ErrorEntry>,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn write<W: std::io::Write>(&self, entries: &[ErrorEntry], mut w: W) -> Result<()> {
+        match self {
+            ExportFormat::Json => serde_json::to_writer_pretty(&mut w, entries)?,
+            ExportFormat::Csv => {
+                fn quote(s: &str) -> String {
+                    format!("\"{}\"", s.replace('"', "\"\""))
+                }
+
+                writeln!(w, "id,timestamp,printer_id,message,description")?;
+                for e in entries {
+                    writeln!(
+                        w,
+                        "{},{},{},{},{}",
+                        e.id,
+                        e.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                        quote(&e.printer_id),
+                        quote(&e.message),
+                        quote(e.description.as_deref().unwrap_or("")),
+                    )?;
+                }
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
 }
 
 impl ErrorDb {
@@ -27,6 +141,11 @@ impl ErrorDb {
 
         let conn = SqlitePool::connect_with(options).await?;
 
+        Self::from_pool(conn).await
+    }
+
+    /// creates the tables if they don't exist yet
+    async fn from_pool(conn: SqlitePool) -> Result<Self> {
         sqlx::query(
             r#"CREATE TABLE IF NOT EXISTS error_log (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -38,6 +157,44 @@ impl ErrorDb {
         .execute(&conn)
         .await?;
 
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS print_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    printer_id TEXT NOT NULL,
+    file TEXT,
+    start_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    end_time TIMESTAMP,
+    final_state TEXT,
+    layer_count INTEGER,
+    subtask_id TEXT,
+    task_id TEXT
+)"#,
+        )
+        .execute(&conn)
+        .await?;
+
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS temp_history (
+    printer_id TEXT NOT NULL,
+    time REAL NOT NULL,
+    nozzle REAL NOT NULL,
+    nozzle_target REAL NOT NULL,
+    bed REAL NOT NULL,
+    bed_target REAL NOT NULL,
+    chamber REAL NOT NULL,
+    chamber_target REAL,
+    progress REAL NOT NULL
+)"#,
+        )
+        .execute(&conn)
+        .await?;
+
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS temp_history_printer_time ON temp_history (printer_id, time)"#,
+        )
+        .execute(&conn)
+        .await?;
+
         // sqlx::query(
         //     "CREATE TABLE IF NOT EXISTS users (
         //         id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -60,4 +217,259 @@ impl ErrorDb {
 
         Ok(())
     }
+
+    /// MARK: queries
+    fn push_filters(qb: &mut QueryBuilder<'_, Sqlite>, query: &ErrorQuery) {
+        const FMT: &str = "%Y-%m-%d %H:%M:%S";
+
+        qb.push(" WHERE 1 = 1");
+        if let Some(id) = query.printer_id.as_ref() {
+            qb.push(" AND printer_id = ").push_bind(id.clone());
+        }
+        if let Some(t) = query.since {
+            qb.push(" AND timestamp >= ")
+                .push_bind(t.format(FMT).to_string());
+        }
+        if let Some(t) = query.until {
+            qb.push(" AND timestamp < ")
+                .push_bind(t.format(FMT).to_string());
+        }
+        if let Some(text) = query.text.as_ref().filter(|t| !t.is_empty()) {
+            qb.push(" AND message LIKE ")
+                .push_bind(format!("%{}%", text));
+        }
+    }
+
+    /// newest first
+    pub async fn query(&self, query: &ErrorQuery) -> Result<Vec<ErrorEntry>> {
+        let mut qb = QueryBuilder::new(
+            "SELECT id, printer_id, timestamp, COALESCE(message, '') AS message FROM error_log",
+        );
+        Self::push_filters(&mut qb, query);
+        qb.push(" ORDER BY timestamp DESC, id DESC LIMIT ")
+            .push_bind(query.limit)
+            .push(" OFFSET ")
+            .push_bind(query.offset);
+
+        let entries = qb
+            .build_query_as::<ErrorEntry>()
+            .fetch_all(&self.db)
+            .await?;
+        Ok(entries)
+    }
+
+    /// number of matches, ignoring limit and offset
+    pub async fn count(&self, query: &ErrorQuery) -> Result<i64> {
+        let mut qb = QueryBuilder::new("SELECT COUNT(*) FROM error_log");
+        Self::push_filters(&mut qb, query);
+
+        let count = qb.build_query_scalar::<i64>().fetch_one(&self.db).await?;
+        Ok(count)
+    }
+
+    /// MARK: print jobs
+    /// returns the row id. an open row for the same job is resumed, so restarting
+    /// the app mid-print doesn't split it, other open rows are closed as aborted
+    pub async fn job_started(
+        &self,
+        printer_id: &str,
+        file: Option<&str>,
+        subtask_id: Option<&str>,
+        task_id: Option<&str>,
+    ) -> Result<i64> {
+        let mut tx = self.db.begin().await?;
+
+        let resumed: Option<i64> = sqlx::query_scalar(
+            r#"SELECT id FROM print_jobs
+WHERE printer_id = ? AND end_time IS NULL AND file IS ? AND subtask_id IS ? AND task_id IS ?
+ORDER BY id DESC LIMIT 1"#,
+        )
+        .bind(printer_id)
+        .bind(file)
+        .bind(subtask_id)
+        .bind(task_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"UPDATE print_jobs
+SET end_time = CURRENT_TIMESTAMP, final_state = ?
+WHERE printer_id = ? AND end_time IS NULL AND id IS NOT ?"#,
+        )
+        .bind(JobEndState::Aborted.as_str())
+        .bind(printer_id)
+        .bind(resumed)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(id) = resumed {
+            tx.commit().await?;
+            debug!("resuming print job {} for {}", id, printer_id);
+            return Ok(id);
+        }
+
+        let result = sqlx::query(
+            r#"INSERT INTO print_jobs (printer_id, file, subtask_id, task_id) VALUES (?, ?, ?, ?)"#,
+        )
+        .bind(printer_id)
+        .bind(file)
+        .bind(subtask_id)
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn job_ended(
+        &self,
+        job_id: i64,
+        state: JobEndState,
+        layer_count: Option<u32>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"UPDATE print_jobs
+SET end_time = CURRENT_TIMESTAMP, final_state = ?, layer_count = ?
+WHERE id = ?"#,
+        )
+        .bind(state.as_str())
+        .bind(layer_count)
+        .bind(job_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// MARK: temp history
+    pub async fn insert_sample(&self, printer_id: &str, sample: &Sample) -> Result<()> {
+        sqlx::query(
+            r#"INSERT INTO temp_history
+(printer_id, time, nozzle, nozzle_target, bed, bed_target, chamber, chamber_target, progress)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(printer_id)
+        .bind(sample.time)
+        .bind(sample.nozzle)
+        .bind(sample.nozzle_target)
+        .bind(sample.bed)
+        .bind(sample.bed_target)
+        .bind(sample.chamber)
+        .bind(sample.chamber_target)
+        .bind(sample.progress)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// the most recent `limit` samples, oldest first
+    pub async fn load_samples(&self, printer_id: &str, limit: usize) -> Result<Vec<Sample>> {
+        let mut samples: Vec<Sample> = sqlx::query_as(
+            r#"SELECT time, nozzle, nozzle_target, bed, bed_target, chamber, chamber_target, progress
+FROM temp_history WHERE printer_id = ? ORDER BY time DESC LIMIT ?"#,
+        )
+        .bind(printer_id)
+        .bind(limit as i64)
+        .fetch_all(&self.db)
+        .await?;
+
+        samples.reverse();
+        Ok(samples)
+    }
+
+    /// delete samples older than `before` (unix seconds)
+    pub async fn prune_samples(&self, before: f64) -> Result<u64> {
+        let result = sqlx::query(r#"DELETE FROM temp_history WHERE time < ?"#)
+            .bind(before)
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// every connection to `:memory:` gets its own database, so only one
+    async fn memory_db() -> ErrorDb {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        ErrorDb::from_pool(pool).await.unwrap()
+    }
+
+    async fn final_state(db: &ErrorDb, id: i64) -> Option<String> {
+        sqlx::query_scalar("SELECT final_state FROM print_jobs WHERE id = ?")
+            .bind(id)
+            .fetch_one(&db.db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn same_job_is_resumed() {
+        let db = memory_db().await;
+
+        let first = db
+            .job_started("p1", Some("a.gcode"), Some("1"), Some("2"))
+            .await
+            .unwrap();
+        let again = db
+            .job_started("p1", Some("a.gcode"), Some("1"), Some("2"))
+            .await
+            .unwrap();
+
+        assert_eq!(first, again);
+        assert_eq!(final_state(&db, first).await, None);
+    }
+
+    #[tokio::test]
+    async fn different_job_aborts_open_row() {
+        let db = memory_db().await;
+
+        let first = db
+            .job_started("p1", Some("a.gcode"), None, None)
+            .await
+            .unwrap();
+        let other_printer = db
+            .job_started("p2", Some("a.gcode"), None, None)
+            .await
+            .unwrap();
+        let second = db
+            .job_started("p1", Some("b.gcode"), None, None)
+            .await
+            .unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(final_state(&db, first).await.as_deref(), Some("aborted"));
+        assert_eq!(final_state(&db, second).await, None);
+        assert_eq!(final_state(&db, other_printer).await, None);
+    }
+
+    #[tokio::test]
+    async fn ended_job_is_not_resumed() {
+        let db = memory_db().await;
+
+        let first = db
+            .job_started("p1", Some("a.gcode"), None, None)
+            .await
+            .unwrap();
+        db.job_ended(first, JobEndState::Finished, Some(10))
+            .await
+            .unwrap();
+        let second = db
+            .job_started("p1", Some("a.gcode"), None, None)
+            .await
+            .unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(final_state(&db, first).await.as_deref(), Some("finished"));
+    }
 }
@@ -0,0 +1,63 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use dashmap::DashMap;
+use std::sync::Arc;
+
+use crate::{
+    config::{printer_id::PrinterId, AppConfig},
+    conn_manager::{PrinterConnCmd, PrinterConnManager, PrinterConnMsg},
+    status::GenericPrinterState,
+    streaming::StreamCmd,
+};
+
+/// run the conn manager without a window, for an always-on machine.
+/// errors and completions are still logged to the database and notified
+pub fn run_headless(config: AppConfig) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async move {
+        let (cmd_tx, cmd_rx) = tokio::sync::mpsc::unbounded_channel::<PrinterConnCmd>();
+        let (msg_tx, mut msg_rx) = tokio::sync::mpsc::unbounded_channel::<PrinterConnMsg>();
+        /// no video without a window, the receiver is only kept so sends don't fail
+        let (stream_tx, _stream_rx) = tokio::sync::mpsc::unbounded_channel::<StreamCmd>();
+
+        let printer_states: Arc<DashMap<PrinterId, GenericPrinterState>> = Arc::new(DashMap::new());
+
+        crate::http_api::spawn(&config, printer_states.clone(), cmd_tx.clone());
+        crate::mqtt_bridge::spawn(&config, printer_states.clone(), cmd_tx.clone());
+
+        let mut conn =
+            PrinterConnManager::new(config, printer_states, cmd_tx, cmd_rx, msg_tx, stream_tx)
+                .await;
+
+        debug!("starting conn manager (headless)");
+        conn.init().await?;
+
+        /// nothing displays these, but the channel still has to be drained
+        tokio::spawn(async move {
+            while let Some(msg) = msg_rx.recv().await {
+                if let PrinterConnMsg::CommandResult(id, cmd_id, result) = msg {
+                    info!("command result: {:?}, {:?} = {:?}", id, cmd_id, result);
+                }
+            }
+        });
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    info!("got ctrl-c, shutting down");
+                    break;
+                }
+                res = conn.run() => {
+                    if let Err(e) = res {
+                        error!("error in conn manager: {:?}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        debug!("restarting conn manager");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
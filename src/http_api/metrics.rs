@@ -0,0 +1,264 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::fmt::Write;
+
+use crate::{
+    config::printer_config::PrinterConfig,
+    status::{bambu_status::PrinterStateBambu, GenericPrinterState, PrinterState},
+};
+
+const PREFIX: &str = "printer_watcher_";
+
+/// every value of the `state` label, so a printer always has one series per state
+const STATES: &[&str] = &[
+    "Idle",
+    "Finished",
+    "Busy",
+    "Printing",
+    "Paused",
+    "Error",
+    "Disconnected",
+    "Unknown",
+];
+
+/// Prometheus text exposition, samples grouped by metric name
+#[derive(Debug, Default)]
+pub struct Exposition {
+    families: Vec<Family>,
+}
+
+#[derive(Debug)]
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<String>,
+}
+
+impl Exposition {
+    pub fn gauge(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        let family = match self.families.iter().position(|f| f.name == name) {
+            Some(i) => &mut self.families[i],
+            None => {
+                self.families.push(Family {
+                    name,
+                    help,
+                    samples: vec![],
+                });
+                self.families.last_mut().unwrap()
+            }
+        };
+
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        family
+            .samples
+            .push(format!("{}{}{{{}}} {}", PREFIX, name, labels, value));
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for f in self.families.iter() {
+            let _ = writeln!(out, "# HELP {}{} {}", PREFIX, f.name, f.help);
+            let _ = writeln!(out, "# TYPE {}{} gauge", PREFIX, f.name);
+            for s in f.samples.iter() {
+                out.push_str(s);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// MARK: printer
+    /// printers that haven't reported yet only get the state series, as Disconnected
+    pub fn add_printer(
+        &mut self,
+        printer: &PrinterConfig,
+        name: &str,
+        state: Option<&GenericPrinterState>,
+    ) {
+        let id = printer.id().to_string();
+        let printer_type = format!("{:?}", printer.printer_type());
+        let labels = [
+            ("id", id.as_str()),
+            ("printer", name),
+            ("type", printer_type.as_str()),
+        ];
+
+        let current = state.map_or(PrinterState::Disconnected.to_text(), |s| s.state.to_text());
+        for s in STATES {
+            self.gauge(
+                "state",
+                "1 for the current printer state",
+                &[&labels[..], &[("state", *s)][..]].concat(),
+                if *s == current { 1. } else { 0. },
+            );
+        }
+
+        let Some(state) = state else {
+            return;
+        };
+
+        self.gauge(
+            "nozzle_temp_celsius",
+            "nozzle temperature",
+            &labels,
+            state.nozzle_temp as f64,
+        );
+        self.gauge(
+            "nozzle_target_celsius",
+            "nozzle target temperature",
+            &labels,
+            state.nozzle_temp_target as f64,
+        );
+        let mut tools = state.nozzle_temps.iter().collect::<Vec<_>>();
+        tools.sort_by_key(|(t, _)| **t);
+        for (tool, temp) in tools {
+            let target = state.nozzle_temps_target.get(tool);
+            let tool = tool.to_string();
+            let tool_labels = [&labels[..], &[("tool", tool.as_str())][..]].concat();
+            self.gauge(
+                "tool_temp_celsius",
+                "nozzle temperature per tool",
+                &tool_labels,
+                *temp as f64,
+            );
+            self.gauge(
+                "tool_target_celsius",
+                "nozzle target temperature per tool",
+                &tool_labels,
+                target.copied().unwrap_or(0.) as f64,
+            );
+        }
+        self.gauge(
+            "bed_temp_celsius",
+            "bed temperature",
+            &labels,
+            state.bed_temp as f64,
+        );
+        self.gauge(
+            "bed_target_celsius",
+            "bed target temperature",
+            &labels,
+            state.bed_temp_target as f64,
+        );
+        self.gauge(
+            "chamber_temp_celsius",
+            "chamber temperature",
+            &labels,
+            state.chamber_temp as f64,
+        );
+        if let Some(t) = state.chamber_temp_target {
+            self.gauge(
+                "chamber_target_celsius",
+                "chamber target temperature",
+                &labels,
+                t as f64,
+            );
+        }
+
+        self.gauge(
+            "progress_percent",
+            "print progress, 0 - 100",
+            &labels,
+            state.progress as f64,
+        );
+        if let Some(t) = state.time_remaining {
+            self.gauge(
+                "remaining_seconds",
+                "estimated time left in the print",
+                &labels,
+                t.num_seconds() as f64,
+            );
+        }
+        if let Some(t) = state.time_printing {
+            self.gauge(
+                "printing_seconds",
+                "time spent on the current print",
+                &labels,
+                t.num_seconds() as f64,
+            );
+        }
+        if let Some((layer, total)) = state.layer {
+            self.gauge("layer", "current layer", &labels, layer as f64);
+            self.gauge("layers_total", "layers in the print", &labels, total as f64);
+        }
+        self.gauge(
+            "fan_speed",
+            "part cooling fan speed, as reported by the printer",
+            &labels,
+            state.fan_speed as f64,
+        );
+        /// 0, 25, 50, 75 or 100, bucketed from the dBm value
+        if let Some(w) = state.wifi_signal {
+            self.gauge(
+                "wifi_signal_percent",
+                "wifi signal strength, in steps of 25%",
+                &labels,
+                w as f64,
+            );
+        }
+
+        if let Some(bambu) = state.state_bambu.as_ref() {
+            self.add_bambu(&labels, bambu);
+        }
+    }
+
+    /// MARK: bambu
+    fn add_bambu(&mut self, labels: &[(&str, &str)], bambu: &PrinterStateBambu) {
+        let fans = [
+            ("heatbreak", bambu.heatbreak_fan_speed),
+            ("cooling", bambu.cooling_fan_speed),
+            ("aux", bambu.aux_fan_speed),
+            ("chamber", bambu.chamber_fan_speed),
+        ];
+        for (fan, speed) in fans {
+            if let Some(speed) = speed {
+                self.gauge(
+                    "bambu_fan_speed",
+                    "Bambu fan speeds, as reported by the printer",
+                    &[labels, &[("fan", fan)][..]].concat(),
+                    speed as f64,
+                );
+            }
+        }
+
+        if let Some(ams) = bambu.ams.as_ref() {
+            let mut units = ams.units.values().collect::<Vec<_>>();
+            units.sort_by_key(|u| u.id);
+            for unit in units {
+                let id = unit.id.to_string();
+                self.gauge(
+                    "ams_humidity",
+                    "AMS humidity index, as reported (1 - 5)",
+                    &[labels, &[("ams", id.as_str())][..]].concat(),
+                    unit.humidity as f64,
+                );
+            }
+        }
+
+        /// e.g. "-45dBm"
+        if let Some(w) = bambu
+            .wifi_signal
+            .as_ref()
+            .and_then(|w| w.trim_end_matches("dBm").parse::<f64>().ok())
+        {
+            self.gauge("wifi_signal_dbm", "wifi signal strength", labels, w);
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,222 @@
+pub mod metrics;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+    routing::{get, post},
+    Router,
+};
+use dashmap::DashMap;
+use futures::Stream;
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    config::{printer_config::PrinterType, printer_id::PrinterId, AppConfig},
+    conn_manager::{CommandId, PrinterConnCmd, WorkerCmd},
+    status::GenericPrinterState,
+};
+
+/// how often each event stream checks for changed printers
+const STREAM_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+type ApiError = (StatusCode, String);
+
+#[derive(Clone)]
+struct ApiState {
+    config: AppConfig,
+    printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
+    cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
+}
+
+#[derive(Debug, Serialize)]
+struct PrinterInfo {
+    id: PrinterId,
+    name: String,
+    printer_type: PrinterType,
+    state: Option<&'static str>,
+}
+
+/// one item of the /api/events stream
+#[derive(Debug, Serialize)]
+struct StateEvent<'a> {
+    id: &'a PrinterId,
+    state: &'a serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandAccepted {
+    command_id: u64,
+}
+
+/// start the server on the current runtime if `[http]` is enabled
+pub fn spawn(
+    config: &AppConfig,
+    printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
+    cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
+) {
+    if !config.http().enabled {
+        return;
+    }
+
+    let bind = config.http().bind.clone();
+    let state = ApiState {
+        config: config.clone(),
+        printer_states,
+        cmd_tx,
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = serve(&bind, state).await {
+            error!("http api stopped: {:?}", e);
+        }
+    });
+}
+
+async fn serve(bind: &str, state: ApiState) -> Result<()> {
+    let app = Router::new()
+        .route("/api/printers", get(list_printers))
+        .route("/api/printers/{id}", get(printer_state))
+        .route("/api/printers/{id}/command", post(send_command))
+        .route("/api/events", get(state_events))
+        .route("/metrics", get(prometheus_metrics))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("failed to bind http api to {}", bind))?;
+    info!("http api listening on {}", bind);
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// MARK: handlers
+async fn list_printers(State(api): State<ApiState>) -> Json<Vec<PrinterInfo>> {
+    let mut out = vec![];
+    for printer in api.config.printers() {
+        let id = printer.id();
+        let state = api.printer_states.get(&id).map(|s| s.state.to_text());
+        out.push(PrinterInfo {
+            name: printer.name().await,
+            printer_type: printer.printer_type(),
+            state,
+            id,
+        });
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(out)
+}
+
+async fn printer_state(
+    State(api): State<ApiState>,
+    Path(id): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, ApiError> {
+    let id = PrinterId::from_id(&id);
+    let Some(state) = api.printer_states.get(&id) else {
+        return Err(not_found(&id));
+    };
+    serde_json::to_value(&*state)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// the result is only reported to the UI, this just returns the command id
+async fn send_command(
+    State(api): State<ApiState>,
+    Path(id): Path<String>,
+    Json(cmd): Json<WorkerCmd>,
+) -> std::result::Result<(StatusCode, Json<CommandAccepted>), ApiError> {
+    let id = PrinterId::from_id(&id);
+    if api.config.get_printer(&id).is_none() {
+        return Err(not_found(&id));
+    }
+
+    let cmd_id = CommandId::next();
+    debug!("http api command: {:?}, {:?} = {:?}", id, cmd_id, cmd);
+    api.cmd_tx
+        .send(PrinterConnCmd::WorkerCmd(id, cmd_id, cmd))
+        .map_err(|_| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "conn manager is not running".to_string(),
+            )
+        })?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(CommandAccepted {
+            command_id: cmd_id.inner(),
+        }),
+    ))
+}
+
+/// server-sent events, a `state` event for every printer when it changes.
+/// new clients get the current state of every printer first
+async fn state_events(
+    State(api): State<ApiState>,
+) -> Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>> {
+    let stream = async_stream::stream! {
+        let mut last: HashMap<PrinterId, serde_json::Value> = HashMap::new();
+        let mut interval = tokio::time::interval(STREAM_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (id, state) in api.changed_states(&mut last) {
+                yield Event::default()
+                    .event("state")
+                    .json_data(StateEvent { id: &id, state: &state });
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Prometheus text format
+async fn prometheus_metrics(State(api): State<ApiState>) -> impl IntoResponse {
+    let mut out = metrics::Exposition::default();
+    for printer in api.config.printers() {
+        let name = printer.name().await;
+        let state = api.printer_states.get(&printer.id());
+        out.add_printer(&printer, &name, state.as_deref());
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        out.render(),
+    )
+}
+
+fn not_found(id: &PrinterId) -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        format!("unknown printer: {}", id.inner()),
+    )
+}
+
+impl ApiState {
+    /// states that differ from `last`, collected so no map guard is held across a yield
+    fn changed_states(
+        &self,
+        last: &mut HashMap<PrinterId, serde_json::Value>,
+    ) -> Vec<(PrinterId, serde_json::Value)> {
+        let mut out = vec![];
+        for entry in self.printer_states.iter() {
+            let Ok(value) = serde_json::to_value(entry.value()) else {
+                continue;
+            };
+            if last.get(entry.key()) != Some(&value) {
+                last.insert(entry.key().clone(), value.clone());
+                out.push((entry.key().clone(), value));
+            }
+        }
+        out
+    }
+}
@@ -12,7 +12,10 @@ pub mod auth;
 pub mod config;
 pub mod conn_manager;
 pub mod error_logging;
+pub mod headless;
+pub mod http_api;
 pub mod logging;
+pub mod mqtt_bridge;
 pub mod notifications;
 // pub mod profiles;
 pub mod fake_printer;
@@ -532,6 +535,14 @@ fn main() -> eframe::Result<()> {
     }
 
     let mut config = AppConfig::load_from_file("config.toml").unwrap_or_default();
+
+    if env::args().any(|a| a == "--headless") {
+        if let Err(e) = headless::run_headless(config) {
+            error!("headless mode failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     // let mut config = AppConfig::default();
     // debug!("loaded config from file");
 
@@ -588,7 +599,10 @@ fn main() -> eframe::Result<()> {
         let link_key = env::var("PRUSA_LINK_KEY").unwrap();
 
         let octo = config::printer_config::PrinterConfigOcto {
+            id: PrinterId::empty(),
+            name: String::new(),
             host: env::var("OCTO_URL").unwrap(),
+            port: 5000,
             token: env::var("OCTO_TOKEN").unwrap(),
         };
 
@@ -624,6 +638,9 @@ fn main() -> eframe::Result<()> {
     std::thread::spawn(|| {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async move {
+            http_api::spawn(&configs2, printer_states2.clone(), cmd_tx2.clone());
+            mqtt_bridge::spawn(&configs2, printer_states2.clone(), cmd_tx2.clone());
+
             let mut conn = conn_manager::PrinterConnManager::new(
                 configs2,
                 printer_states2,
@@ -728,17 +745,11 @@ async fn main() -> Result<()> {
         id: id.clone(),
         name: "test_octo_printer".to_string(),
         host,
+        port: 5000,
         token,
     };
-    let printer = Arc::new(RwLock::new(printer));
 
-    let mut client = conn_manager::conn_octoprint::OctoClientLocal::new(
-        printer,
-        worker_msg_tx,
-        worker_cmd_rx,
-        kill_rx,
-        None,
-    )?;
+    let mut client = conn_manager::conn_octoprint::OctoClientLocal::new(printer)?;
 
     // let cmd = conn_manager::conn_octoprint::octo_commands::OctoCmd::Home {
     //     x: true,
@@ -749,7 +760,11 @@ async fn main() -> Result<()> {
     // let cmd = conn_manager::conn_octoprint::octo_commands::OctoCmd::ParkTool;
 
     // let cmd = conn_manager::conn_octoprint::octo_commands::OctoCmd::unload_filament(0);
-    let cmd = conn_manager::conn_octoprint::octo_commands::OctoCmd::load_pla(vec![0]);
+    let cmd = conn_manager::conn_octoprint::octo_commands::OctoCmd::load_filament(
+        [0],
+        conn_manager::toolchanger::FilamentType::new("PLA")?,
+        None,
+    );
 
     let res = client.send_command(&cmd).await?;
 
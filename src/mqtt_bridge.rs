@@ -0,0 +1,452 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use dashmap::DashMap;
+use rumqttc::{AsyncClient, Event, EventLoop, Incoming, LastWill, MqttOptions, QoS};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    config::{printer_id::PrinterId, AppConfig, MqttConfig},
+    conn_manager::{CommandId, PrinterConnCmd, WorkerCmd},
+    status::GenericPrinterState,
+};
+
+/// how often printer states are checked for changes
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `<prefix>/<id>/temps`
+#[derive(Debug, Serialize)]
+struct Temps {
+    nozzle: f32,
+    nozzle_target: f32,
+    bed: f32,
+    bed_target: f32,
+    chamber: f32,
+    chamber_target: Option<f32>,
+    /// toolchangers and multi-extruder printers, tool index -> (temp, target)
+    tools: BTreeMap<usize, (f32, f32)>,
+}
+
+/// `<prefix>/<id>/progress`
+#[derive(Debug, Serialize)]
+struct Progress<'a> {
+    state: &'static str,
+    progress: f32,
+    file: Option<&'a str>,
+    layer: Option<u32>,
+    total_layers: Option<u32>,
+    remaining_secs: Option<i64>,
+    printing_secs: Option<i64>,
+}
+
+impl Temps {
+    fn new(state: &GenericPrinterState) -> Self {
+        Self {
+            nozzle: state.nozzle_temp,
+            nozzle_target: state.nozzle_temp_target,
+            bed: state.bed_temp,
+            bed_target: state.bed_temp_target,
+            chamber: state.chamber_temp,
+            chamber_target: state.chamber_temp_target,
+            tools: state
+                .nozzle_temps
+                .iter()
+                .map(|(t, temp)| {
+                    let target = state.nozzle_temps_target.get(t).copied().unwrap_or(0.);
+                    (*t, (*temp, target))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> Progress<'a> {
+    fn new(state: &'a GenericPrinterState) -> Self {
+        Self {
+            state: state.state.to_text(),
+            progress: state.progress,
+            file: state.current_file.as_deref(),
+            layer: state.layer.map(|(l, _)| l),
+            total_layers: state.layer.map(|(_, t)| t),
+            remaining_secs: state.time_remaining.map(|t| t.num_seconds()),
+            printing_secs: state.time_printing.map(|t| t.num_seconds()),
+        }
+    }
+}
+
+/// publishes every printer's state to an external broker as retained JSON,
+/// and forwards commands from `<prefix>/<id>/cmd` to the conn manager
+pub struct MqttBridge {
+    config: AppConfig,
+    prefix: String,
+    client: AsyncClient,
+    printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
+    cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
+    /// last payload sent on each topic
+    published: HashMap<String, Vec<u8>>,
+}
+
+/// start the bridge on the current runtime if `[mqtt]` is enabled
+pub fn spawn(
+    config: &AppConfig,
+    printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
+    cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
+) {
+    if !config.mqtt().enabled {
+        return;
+    }
+
+    let (bridge, eventloop) = MqttBridge::new(config.clone(), printer_states, cmd_tx);
+    tokio::spawn(bridge.run(eventloop));
+}
+
+/// new
+impl MqttBridge {
+    fn new(
+        config: AppConfig,
+        printer_states: Arc<DashMap<PrinterId, GenericPrinterState>>,
+        cmd_tx: tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>,
+    ) -> (Self, EventLoop) {
+        let cfg: &MqttConfig = config.mqtt();
+        let prefix = cfg.topic_prefix.trim_end_matches('/').to_string();
+
+        let client_id = format!("printer_watcher-{}", nanoid::nanoid!(8));
+        let mut options = MqttOptions::new(client_id, &cfg.host, cfg.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(user) = cfg.username.as_ref() {
+            options.set_credentials(user, cfg.password.as_deref().unwrap_or(""));
+        }
+        options.set_last_will(LastWill::new(
+            format!("{}/status", prefix),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+
+        /// roomy, publishing doesn't wait on the eventloop
+        let (client, eventloop) = AsyncClient::new(options, 100);
+
+        let out = Self {
+            config,
+            prefix,
+            client,
+            printer_states,
+            cmd_tx,
+            published: HashMap::new(),
+        };
+        (out, eventloop)
+    }
+}
+
+/// run
+impl MqttBridge {
+    async fn run(mut self, mut eventloop: EventLoop) {
+        info!(
+            "mqtt bridge connecting to {}:{}",
+            self.config.mqtt().host,
+            self.config.mqtt().port
+        );
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                event = eventloop.poll() => {
+                    match event {
+                        Ok(event) => {
+                            if let Err(e) = self.handle_event(event) {
+                                error!("mqtt bridge: {:?}", e);
+                            }
+                        }
+                        Err(e) => {
+                            /// polling again reconnects
+                            warn!("mqtt bridge connection error: {:?}", e);
+                            tokio::time::sleep(Duration::from_secs(5)).await;
+                        }
+                    }
+                }
+                _ = interval.tick() => self.publish_changes(),
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Incoming(Incoming::ConnAck(c)) => {
+                if c.code != rumqttc::ConnectReturnCode::Success {
+                    bail!("connection refused: {:?}", c.code);
+                }
+                info!("mqtt bridge connected");
+                /// the broker may have restarted, send everything again
+                self.published.clear();
+                self.client
+                    .try_subscribe(format!("{}/+/cmd", self.prefix), QoS::AtLeastOnce)?;
+                self.client.try_publish(
+                    format!("{}/status", self.prefix),
+                    QoS::AtLeastOnce,
+                    true,
+                    "online",
+                )?;
+            }
+            Event::Incoming(Incoming::Publish(p)) => {
+                /// a retained command would run again on every reconnect
+                if p.retain {
+                    bail!("ignoring retained command on {}", p.topic);
+                }
+                self.handle_command(&p.topic, &p.payload)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `<prefix>/<id>/cmd`, payload is a WorkerCmd as JSON
+    fn handle_command(&self, topic: &str, payload: &[u8]) -> Result<()> {
+        let (id, cmd) = parse_command(&self.prefix, topic, payload)?;
+
+        if self.config.get_printer(&id).is_none() {
+            bail!("command for unknown printer: {:?}", id);
+        }
+
+        let cmd_id = CommandId::next();
+        debug!("mqtt bridge command: {:?}, {:?} = {:?}", id, cmd_id, cmd);
+        self.cmd_tx
+            .send(PrinterConnCmd::WorkerCmd(id, cmd_id, cmd))?;
+        Ok(())
+    }
+
+    fn publish_changes(&mut self) {
+        let mut topics = vec![];
+        for entry in self.printer_states.iter() {
+            let (id, state) = (entry.key().inner(), entry.value());
+            for (topic, payload) in printer_topics(&self.prefix, id, state) {
+                match payload {
+                    Ok(payload) => topics.push((topic, payload)),
+                    Err(e) => error!("failed to serialize {}: {:?}", topic, e),
+                }
+            }
+        }
+
+        for (topic, payload) in topics {
+            if self.published.get(&topic) == Some(&payload) {
+                continue;
+            }
+            /// on failure the topic stays stale and is retried next tick
+            match self
+                .client
+                .try_publish(topic.as_str(), QoS::AtLeastOnce, true, payload.clone())
+            {
+                Ok(()) => {
+                    self.published.insert(topic, payload);
+                }
+                Err(e) => {
+                    trace!("mqtt bridge publish failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// MARK: topics
+/// the retained topics for one printer, `<prefix>/<id>/state`, `temps` and `progress`
+fn printer_topics(
+    prefix: &str,
+    id: &str,
+    state: &GenericPrinterState,
+) -> Vec<(String, serde_json::Result<Vec<u8>>)> {
+    [
+        ("state", serde_json::to_vec(state)),
+        ("temps", serde_json::to_vec(&Temps::new(state))),
+        ("progress", serde_json::to_vec(&Progress::new(state))),
+    ]
+    .into_iter()
+    .map(|(name, payload)| (format!("{}/{}/{}", prefix, id, name), payload))
+    .collect()
+}
+
+/// printer and command from a message on `<prefix>/<id>/cmd`
+fn parse_command(prefix: &str, topic: &str, payload: &[u8]) -> Result<(PrinterId, WorkerCmd)> {
+    let id = topic
+        .strip_prefix(prefix)
+        .and_then(|t| t.strip_prefix('/'))
+        .and_then(|t| t.strip_suffix("/cmd"))
+        .filter(|id| !id.is_empty() && !id.contains('/'))
+        .context(format!("unexpected topic: {}", topic))?;
+
+    let cmd: WorkerCmd = serde_json::from_slice(payload)
+        .with_context(|| format!("bad command: {}", String::from_utf8_lossy(payload)))?;
+
+    Ok((PrinterId::from_id(id), cmd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    use crate::config::printer_config::{PrinterConfig, PrinterConfigKlipper};
+
+    #[test]
+    fn topic_layout() {
+        let state = GenericPrinterState {
+            nozzle_temp: 210.5,
+            nozzle_temps: [(1, 180.)].into(),
+            current_file: Some("cube.gcode".to_string()),
+            ..Default::default()
+        };
+
+        let topics = printer_topics("pw", "abc", &state)
+            .into_iter()
+            .map(|(t, p)| (t, serde_json::from_slice::<Value>(&p.unwrap()).unwrap()))
+            .collect::<Vec<_>>();
+
+        let names = topics.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["pw/abc/state", "pw/abc/temps", "pw/abc/progress"]);
+
+        let temps = &topics[1].1;
+        assert_eq!(temps["nozzle"], 210.5);
+        assert_eq!(temps["tools"]["1"], serde_json::json!([180.0, 0.0]));
+
+        let progress = &topics[2].1;
+        assert_eq!(progress["state"], state.state.to_text());
+        assert_eq!(progress["file"], "cube.gcode");
+        assert_eq!(progress["layer"], Value::Null);
+    }
+
+    #[test]
+    fn parse_commands() {
+        let (id, cmd) = parse_command("pw", "pw/abc/cmd", br#"{"cmd": "pause"}"#).unwrap();
+        assert_eq!(id, PrinterId::from_id("abc"));
+        assert_eq!(cmd, WorkerCmd::Pause);
+
+        let (_, cmd) = parse_command(
+            "pw/nested",
+            "pw/nested/abc/cmd",
+            br#"{"cmd": "set_bed_temp", "args": 60.0}"#,
+        )
+        .unwrap();
+        assert_eq!(cmd, WorkerCmd::SetBedTemp(60.));
+    }
+
+    #[test]
+    fn parse_command_rejects() {
+        let pause = br#"{"cmd": "pause"}"#;
+        for topic in [
+            "other/abc/cmd",
+            "pwx/abc/cmd",
+            "pw/abc/state",
+            "pw//cmd",
+            "pw/a/b/cmd",
+            "pw/cmd",
+        ] {
+            assert!(parse_command("pw", topic, pause).is_err(), "{}", topic);
+        }
+
+        for payload in [&b"pause"[..], b"{}", br#"{"cmd": "fly"}"#, b"\xff"] {
+            assert!(parse_command("pw", "pw/abc/cmd", payload).is_err());
+        }
+    }
+
+    /// publishes a printer and sends it a command through a real broker
+    #[test]
+    #[ignore = "needs an MQTT broker on localhost:1883"]
+    fn broker_round_trip() {
+        use tokio::time::timeout;
+
+        let config = AppConfig::empty();
+        let printer = PrinterConfigKlipper::new("mqtt test".to_string(), "localhost".to_string());
+        let id = printer.id.clone();
+        config
+            .add_printer_blocking(PrinterConfig::Klipper(
+                id.clone(),
+                Arc::new(tokio::sync::RwLock::new(printer)),
+            ))
+            .unwrap();
+
+        let states = Arc::new(DashMap::new());
+        states.insert(id.clone(), GenericPrinterState::default());
+        let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let (bridge, bridge_loop) = MqttBridge::new(config.clone(), states, cmd_tx);
+            let prefix = bridge.prefix.clone();
+            tokio::spawn(bridge.run(bridge_loop));
+
+            let options = MqttOptions::new(
+                format!("printer_watcher-test-{}", nanoid::nanoid!(8)),
+                "localhost",
+                1883,
+            );
+            let (client, mut eventloop) = AsyncClient::new(options, 10);
+            client
+                .subscribe(format!("{}/status", prefix), QoS::AtLeastOnce)
+                .await
+                .unwrap();
+            client
+                .subscribe(
+                    format!("{}/{}/progress", prefix, id.inner()),
+                    QoS::AtLeastOnce,
+                )
+                .await
+                .unwrap();
+
+            /// a live "online" comes after the bridge's subscription, a retained one may be stale
+            let mut online = false;
+            let mut progress = None;
+            timeout(Duration::from_secs(10), async {
+                while !online || progress.is_none() {
+                    let Event::Incoming(Incoming::Publish(p)) = eventloop.poll().await.unwrap()
+                    else {
+                        continue;
+                    };
+                    if p.topic.ends_with("/status") {
+                        online |= !p.retain && &p.payload[..] == b"online";
+                    } else {
+                        progress = Some(serde_json::from_slice::<Value>(&p.payload).unwrap());
+                    }
+                }
+            })
+            .await
+            .expect("no state from the bridge");
+            assert!(progress.unwrap()["state"].is_string());
+
+            /// the bridge takes the next id after this one
+            let sent_after = CommandId::next();
+            client
+                .publish(
+                    format!("{}/{}/cmd", prefix, id.inner()),
+                    QoS::AtLeastOnce,
+                    false,
+                    r#"{"cmd": "pause"}"#,
+                )
+                .await
+                .unwrap();
+
+            let cmd = timeout(Duration::from_secs(10), async {
+                loop {
+                    tokio::select! {
+                        cmd = cmd_rx.recv() => break cmd,
+                        event = eventloop.poll() => { event.unwrap(); }
+                    }
+                }
+            })
+            .await
+            .expect("command not forwarded")
+            .unwrap();
+
+            match cmd {
+                PrinterConnCmd::WorkerCmd(printer_id, cmd_id, WorkerCmd::Pause) => {
+                    assert_eq!(printer_id, id);
+                    assert!(cmd_id.inner() > sent_after.inner());
+                }
+                other => panic!("unexpected command: {:?}", other),
+            }
+        });
+    }
+}
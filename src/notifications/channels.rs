@@ -0,0 +1,254 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use super::Notification;
+use crate::config::notify_config::ChannelKind;
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// a place notifications can be delivered to
+pub trait Notifier: Send + Sync {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a>;
+}
+
+/// MARK: from config
+pub fn from_config(kind: &ChannelKind, client: &reqwest::Client) -> Result<Arc<dyn Notifier>> {
+    let out: Arc<dyn Notifier> = match kind {
+        ChannelKind::Desktop => Arc::new(DesktopNotifier),
+        ChannelKind::Webhook { url, headers } => Arc::new(WebhookNotifier {
+            client: client.clone(),
+            url: url.clone(),
+            headers: headers.clone(),
+        }),
+        ChannelKind::Ntfy {
+            server,
+            topic,
+            token,
+        } => Arc::new(NtfyNotifier {
+            client: client.clone(),
+            server: server.trim_end_matches('/').to_string(),
+            topic: topic.clone(),
+            token: token.clone(),
+        }),
+        ChannelKind::Gotify { server, token } => Arc::new(GotifyNotifier {
+            client: client.clone(),
+            server: server.trim_end_matches('/').to_string(),
+            token: token.clone(),
+        }),
+        ChannelKind::Email {
+            host,
+            port,
+            tls,
+            username,
+            password,
+            from,
+            to,
+        } => {
+            let mut transport = if *tls {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+            } else {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+            };
+            if let Some(port) = port {
+                transport = transport.port(*port);
+            }
+            if let Some(username) = username {
+                transport = transport.credentials(Credentials::new(
+                    username.clone(),
+                    password.clone().unwrap_or_default(),
+                ));
+            }
+            ensure!(!to.is_empty(), "email channel has no recipients");
+            Arc::new(EmailNotifier {
+                transport: transport.build(),
+                from: from.parse().context("bad from address")?,
+                to: to
+                    .iter()
+                    .map(|t| t.parse().context("bad to address"))
+                    .collect::<Result<_>>()?,
+            })
+        }
+        ChannelKind::Discord { url } => Arc::new(ChatNotifier {
+            client: client.clone(),
+            url: url.clone(),
+            discord: true,
+        }),
+        ChannelKind::Slack { url } => Arc::new(ChatNotifier {
+            client: client.clone(),
+            url: url.clone(),
+            discord: false,
+        }),
+    };
+    Ok(out)
+}
+
+/// MARK: desktop
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        let (title, body) = (n.title.clone(), n.body.clone());
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                notify_rust::Notification::new()
+                    .summary(&title)
+                    .body(&body)
+                    .appname("Printer Watcher")
+                    .timeout(0)
+                    .show()
+                    .map(|_| ())
+            })
+            .await??;
+            Ok(())
+        })
+    }
+}
+
+/// MARK: webhook
+/// POSTs the Notification as JSON
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+}
+
+impl Notifier for WebhookNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let mut req = self.client.post(&self.url).json(n);
+            for (k, v) in self.headers.iter() {
+                req = req.header(k, v);
+            }
+            req.send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// MARK: ntfy
+pub struct NtfyNotifier {
+    client: reqwest::Client,
+    server: String,
+    topic: String,
+    token: Option<String>,
+}
+
+impl Notifier for NtfyNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            /// JSON instead of headers, so titles aren't limited to ASCII
+            let (priority, tag) = if n.event.is_critical() {
+                (4, "warning")
+            } else {
+                (3, "printer")
+            };
+            let body = serde_json::json!({
+                "topic": self.topic,
+                "title": n.title,
+                "message": n.body,
+                "priority": priority,
+                "tags": [tag],
+            });
+
+            let mut req = self.client.post(&self.server).json(&body);
+            if let Some(token) = self.token.as_ref() {
+                req = req.bearer_auth(token);
+            }
+            req.send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// MARK: gotify
+pub struct GotifyNotifier {
+    client: reqwest::Client,
+    server: String,
+    token: String,
+}
+
+impl Notifier for GotifyNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "title": n.title,
+                "message": n.body,
+                "priority": if n.event.is_critical() { 8 } else { 5 },
+            });
+
+            self.client
+                .post(format!("{}/message", self.server))
+                .header("X-Gotify-Key", &self.token)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// MARK: email
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Notifier for EmailNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let mut builder = Message::builder()
+                .from(self.from.clone())
+                .subject(&n.title)
+                .header(ContentType::TEXT_PLAIN);
+            for to in self.to.iter() {
+                builder = builder.to(to.clone());
+            }
+            let message = builder.body(n.body.clone())?;
+
+            self.transport.send(message).await?;
+            Ok(())
+        })
+    }
+}
+
+/// MARK: discord, slack
+pub struct ChatNotifier {
+    client: reqwest::Client,
+    url: String,
+    /// otherwise slack
+    discord: bool,
+}
+
+impl Notifier for ChatNotifier {
+    fn send<'a>(&'a self, n: &'a Notification) -> SendFuture<'a> {
+        Box::pin(async move {
+            let body = if self.discord {
+                serde_json::json!({
+                    "username": "Printer Watcher",
+                    "content": format!("**{}**\n{}", n.title, n.body),
+                })
+            } else {
+                serde_json::json!({
+                    "text": format!("*{}*\n{}", n.title, n.body),
+                })
+            };
+
+            self.client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,293 @@
+pub mod channels;
+pub mod quiet_hours;
+pub mod rules;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    config::{
+        notify_config::{NotifyConfig, RouteConfig},
+        printer_id::PrinterId,
+    },
+    error_logging::error_db::ErrorDb,
+};
+use channels::Notifier;
+use quiet_hours::QuietHours;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    PrintComplete,
+    PrinterError,
+    StreamError,
+    /// from `[[notify.rules]]`
+    Paused,
+    FirstLayer,
+    Progress,
+    Eta,
+    TempDeviation,
+    Disconnected,
+    AmsHumidity,
+    /// a print will finish during quiet hours
+    EtaOvernight,
+    /// notifications held during quiet hours
+    Digest,
+}
+
+impl NotifyEvent {
+    /// sent with a higher priority where the channel supports it
+    pub fn is_critical(&self) -> bool {
+        matches!(
+            self,
+            NotifyEvent::PrinterError | NotifyEvent::StreamError | NotifyEvent::TempDeviation
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub printer_id: PrinterId,
+    pub printer_name: String,
+    pub title: String,
+    pub body: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Notification {
+    pub fn new(
+        event: NotifyEvent,
+        printer_id: &PrinterId,
+        printer_name: &str,
+        title: String,
+        body: String,
+    ) -> Self {
+        Self {
+            event,
+            printer_id: printer_id.clone(),
+            printer_name: printer_name.to_string(),
+            title,
+            body,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+/// MARK: NotifyRouter
+/// sends each notification to the channels whose routes match it
+#[derive(Clone)]
+pub struct NotifyRouter {
+    channels: Vec<(String, Arc<dyn Notifier>)>,
+    routes: Vec<RouteConfig>,
+    quiet: Arc<Mutex<QuietHours>>,
+}
+
+impl NotifyRouter {
+    /// channels that fail to build are logged and skipped
+    pub fn new(config: &NotifyConfig) -> Self {
+        let quiet = Arc::new(Mutex::new(QuietHours::new(config.quiet_hours.clone())));
+
+        if config.channels.is_empty() {
+            return Self {
+                channels: vec![("desktop".to_string(), Arc::new(channels::DesktopNotifier))],
+                routes: vec![],
+                quiet,
+            };
+        }
+
+        let client = reqwest::Client::new();
+        let mut out = vec![];
+        for c in config.channels.iter() {
+            match channels::from_config(&c.kind, &client) {
+                Ok(n) => out.push((c.name.clone(), n)),
+                Err(e) => error!("notification channel {:?}: {:?}", c.name, e),
+            }
+        }
+
+        for route in config.routes.iter() {
+            for name in route.channels.iter() {
+                if !config.channels.iter().any(|c| &c.name == name) {
+                    warn!("notification route uses unknown channel: {:?}", name);
+                }
+            }
+        }
+
+        Self {
+            channels: out,
+            routes: config.routes.clone(),
+            quiet,
+        }
+    }
+
+    pub fn quiet_hours(&self) -> &Mutex<QuietHours> {
+        &self.quiet
+    }
+
+    fn matches(route: &RouteConfig, n: &Notification) -> bool {
+        let printer = route.printers.is_empty()
+            || route
+                .printers
+                .iter()
+                .any(|p| p == n.printer_id.inner().as_str() || p == &n.printer_name);
+        let event = route.events.is_empty() || route.events.contains(&n.event);
+        printer && event
+    }
+
+    fn targets(&self, n: &Notification) -> Vec<(String, Arc<dyn Notifier>)> {
+        if self.routes.is_empty() {
+            return self.channels.clone();
+        }
+
+        self.channels
+            .iter()
+            .filter(|(name, _)| {
+                self.routes
+                    .iter()
+                    .any(|r| r.channels.contains(name) && Self::matches(r, n))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// doesn't wait for delivery, failures are logged
+    pub fn send(&self, n: Notification) {
+        if self.quiet.lock().intercept(&n) {
+            return;
+        }
+
+        let n = Arc::new(n);
+        for (name, channel) in self.targets(&n) {
+            Self::spawn_send(name, channel, n.clone());
+        }
+    }
+
+    /// after quiet hours, sends one message per channel listing everything it missed
+    pub fn send_held(&self) {
+        let held = self.quiet.lock().take_held();
+        if held.is_empty() {
+            return;
+        }
+
+        let mut per_channel: HashMap<String, (Arc<dyn Notifier>, Vec<&Notification>)> =
+            HashMap::new();
+        for n in held.iter() {
+            for (name, channel) in self.targets(n) {
+                per_channel
+                    .entry(name)
+                    .or_insert((channel, vec![]))
+                    .1
+                    .push(n);
+            }
+        }
+
+        for (name, (channel, ns)) in per_channel {
+            let body = ns
+                .iter()
+                .map(|n| {
+                    format!(
+                        "{} {}: {}",
+                        n.timestamp.with_timezone(&chrono::Local).format("%H:%M"),
+                        n.title,
+                        n.body
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let digest = Notification::new(
+                NotifyEvent::Digest,
+                &PrinterId::empty(),
+                "",
+                format!("{} notifications during quiet hours", ns.len()),
+                body,
+            );
+            Self::spawn_send(name, channel, Arc::new(digest));
+        }
+    }
+
+    fn spawn_send(name: String, channel: Arc<dyn Notifier>, n: Arc<Notification>) {
+        tokio::spawn(async move {
+            if let Err(e) = channel.send(&n).await {
+                error!("failed to send notification to {:?}: {:?}", name, e);
+            }
+        });
+    }
+}
+
+/// MARK: alerts
+pub async fn alert_print_complete(
+    error_db: &ErrorDb,
+    notify: &NotifyRouter,
+    printer_id: &PrinterId,
+    name: &str,
+    file: &str,
+) {
+    error_db
+        .insert(printer_id.inner(), &format!("Print complete: {}", file))
+        .await
+        .unwrap();
+
+    notify.send(Notification::new(
+        NotifyEvent::PrintComplete,
+        printer_id,
+        name,
+        format!("Print Complete on {}", name),
+        file.to_string(),
+    ));
+}
+
+pub async fn alert_printer_error(
+    error_db: &ErrorDb,
+    notify: &NotifyRouter,
+    printer_id: &PrinterId,
+    name: &str,
+    code: i64,
+    error: &str,
+) {
+    error_db
+        .insert(
+            printer_id.inner(),
+            &format!("error (code {}): {}", code, error),
+        )
+        .await
+        .unwrap();
+
+    notify.send(Notification::new(
+        NotifyEvent::PrinterError,
+        printer_id,
+        name,
+        format!("Printer Error: {}", name),
+        format!("Printer error: {:?}\n\nError: {:?}", name, error),
+    ));
+}
+
+/// from an alert rule or the quiet hours ETA check
+pub async fn alert_rule(error_db: &ErrorDb, notify: &NotifyRouter, n: Notification) {
+    if let Err(e) = error_db
+        .insert(n.printer_id.inner(), &format!("{}: {}", n.title, n.body))
+        .await
+    {
+        error!("failed to log alert: {:?}", e);
+    }
+
+    notify.send(n);
+}
+
+pub async fn alert_printer_stream_error(
+    notify: &NotifyRouter,
+    printer_id: &PrinterId,
+    name: &str,
+    error: &str,
+) {
+    notify.send(Notification::new(
+        NotifyEvent::StreamError,
+        printer_id,
+        name,
+        format!("Stream Error: {}", name),
+        format!("Stream error: {:?}\n\nError: {:?}", name, error),
+    ));
+}
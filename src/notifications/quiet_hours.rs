@@ -0,0 +1,75 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use chrono::{DateTime, Local, Timelike};
+
+use super::Notification;
+use crate::config::notify_config::{QuietHoursConfig, QuietMode};
+
+/// holds back non-critical notifications between bed and wake time
+#[derive(Debug)]
+pub struct QuietHours {
+    config: QuietHoursConfig,
+    /// hours of the day
+    wake: u32,
+    bed: u32,
+    held: Vec<Notification>,
+}
+
+impl QuietHours {
+    pub fn new(config: QuietHoursConfig) -> Self {
+        Self {
+            wake: config.wake,
+            bed: config.bed,
+            config,
+            held: vec![],
+        }
+    }
+
+    pub fn set_hours(&mut self, wake: u32, bed: u32) {
+        self.wake = wake;
+        self.bed = bed;
+    }
+
+    pub fn eta_warning(&self) -> bool {
+        self.config.enabled && self.config.eta_warning
+    }
+
+    /// also true for times tomorrow night, only the hour is checked
+    pub fn is_quiet_at(&self, time: DateTime<Local>) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        let h = time.hour();
+        if self.bed > self.wake {
+            h >= self.bed || h < self.wake
+        } else {
+            h >= self.bed && h < self.wake
+        }
+    }
+
+    /// true if `n` was held or dropped instead of being sent now
+    pub fn intercept(&mut self, n: &Notification) -> bool {
+        if n.event.is_critical() || !self.is_quiet_at(Local::now()) {
+            return false;
+        }
+        match self.config.mode {
+            QuietMode::Batch => {
+                debug!("holding notification until wake time: {:?}", n.title);
+                self.held.push(n.clone());
+            }
+            QuietMode::Suppress => {
+                debug!("dropping notification during quiet hours: {:?}", n.title);
+            }
+        }
+        true
+    }
+
+    /// held notifications, once quiet hours are over
+    pub fn take_held(&mut self) -> Vec<Notification> {
+        if self.held.is_empty() || self.is_quiet_at(Local::now()) {
+            return vec![];
+        }
+        std::mem::take(&mut self.held)
+    }
+}
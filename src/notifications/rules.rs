@@ -0,0 +1,310 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::{Notification, NotifyEvent};
+use crate::{
+    config::{
+        notify_config::{RuleConfig, RuleKind},
+        printer_id::PrinterId,
+    },
+    status::{bambu_status::AmsStatus, GenericPrinterState, PrinterState},
+};
+
+/// checks the `[[notify.rules]]` against each printer's state.
+/// a rule fires once when its condition becomes true, then not again until the
+/// condition clears and the cooldown has passed, so flapping doesn't spam
+#[derive(Debug, Default)]
+pub struct AlertRules {
+    rules: Vec<RuleConfig>,
+    /// (printer, rule index)
+    states: HashMap<(PrinterId, usize), RuleState>,
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    /// condition held at the last check
+    active: bool,
+    /// when the condition started, for rules that have to hold for a while
+    since: Option<Instant>,
+    last_fired: Option<Instant>,
+}
+
+/// result of evaluating one rule
+struct Check {
+    /// condition is true right now
+    raw: bool,
+    /// how long it has to stay true before firing
+    hold: Duration,
+    title: String,
+    body: String,
+}
+
+impl Check {
+    fn new(raw: bool, title: String, body: String) -> Self {
+        Self {
+            raw,
+            hold: Duration::ZERO,
+            title,
+            body,
+        }
+    }
+
+    fn hold(mut self, hold: Duration) -> Self {
+        self.hold = hold;
+        self
+    }
+}
+
+impl RuleKind {
+    pub fn event(&self) -> NotifyEvent {
+        match self {
+            RuleKind::Paused => NotifyEvent::Paused,
+            RuleKind::FirstLayer => NotifyEvent::FirstLayer,
+            RuleKind::Progress { .. } => NotifyEvent::Progress,
+            RuleKind::Eta { .. } => NotifyEvent::Eta,
+            RuleKind::TempDeviation { .. } => NotifyEvent::TempDeviation,
+            RuleKind::Disconnected { .. } => NotifyEvent::Disconnected,
+            RuleKind::AmsHumidity { .. } => NotifyEvent::AmsHumidity,
+        }
+    }
+}
+
+impl AlertRules {
+    pub fn new(rules: Vec<RuleConfig>) -> Self {
+        Self {
+            rules,
+            states: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `last_seen` is when the printer last sent an update, None if it never has
+    pub fn check(
+        &mut self,
+        id: &PrinterId,
+        name: &str,
+        state: Option<&GenericPrinterState>,
+        last_seen: Option<Instant>,
+        now: Instant,
+    ) -> Vec<Notification> {
+        let mut out = vec![];
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.printers.is_empty()
+                && !rule
+                    .printers
+                    .iter()
+                    .any(|p| p == id.inner().as_str() || p == name)
+            {
+                continue;
+            }
+
+            let Some(check) = Self::evaluate(&rule.kind, name, state, last_seen, now) else {
+                continue;
+            };
+
+            let rs = self.states.entry((id.clone(), i)).or_default();
+            let since = if check.raw {
+                *rs.since.get_or_insert(now)
+            } else {
+                rs.since = None;
+                now
+            };
+            let holds = check.raw && now.duration_since(since) >= check.hold;
+
+            if !holds {
+                rs.active = false;
+                continue;
+            }
+            if rs.active {
+                continue;
+            }
+            rs.active = true;
+
+            let cooldown = Duration::from_secs(rule.cooldown_secs);
+            if rs
+                .last_fired
+                .is_some_and(|t| now.duration_since(t) < cooldown)
+            {
+                debug!("alert rule {:?} for {:?} in cooldown", rule.kind, name);
+                continue;
+            }
+            rs.last_fired = Some(now);
+
+            out.push(Notification::new(
+                rule.kind.event(),
+                id,
+                name,
+                check.title,
+                check.body,
+            ));
+        }
+
+        out
+    }
+
+    /// MARK: evaluate
+    /// None when the rule doesn't apply, e.g. no AMS
+    fn evaluate(
+        kind: &RuleKind,
+        name: &str,
+        state: Option<&GenericPrinterState>,
+        last_seen: Option<Instant>,
+        now: Instant,
+    ) -> Option<Check> {
+        if let RuleKind::Disconnected { seconds } = kind {
+            let limit = Duration::from_secs(*seconds);
+            let title = format!("{} disconnected", name);
+            let body = if *seconds < 120 {
+                format!("No updates for {} seconds", seconds)
+            } else {
+                format!("No updates for {} minutes", seconds / 60)
+            };
+            /// never seen, count from the first check instead
+            let check = match last_seen {
+                Some(t) => Check::new(now.duration_since(t) >= limit, title, body),
+                None => Check::new(true, title, body).hold(limit),
+            };
+            return Some(check);
+        }
+
+        let state = state?;
+        let printing = state.state == PrinterState::Printing;
+        let file = state.current_file.clone().unwrap_or_default();
+
+        let check = match kind {
+            RuleKind::Disconnected { .. } => unreachable!(),
+            RuleKind::Paused => Check::new(
+                state.state == PrinterState::Paused,
+                format!("{} paused", name),
+                file,
+            ),
+            RuleKind::FirstLayer => Check::new(
+                printing && state.layer.is_some_and(|(l, _)| l >= 2),
+                format!("First layer done on {}", name),
+                file,
+            ),
+            RuleKind::Progress { percent } => Check::new(
+                matches!(state.state, PrinterState::Printing | PrinterState::Paused)
+                    && state.progress >= *percent,
+                format!("{} at {:.0}%", name, state.progress),
+                file,
+            ),
+            RuleKind::Eta { minutes } => {
+                let remaining = state.time_remaining.map(|t| t.num_minutes());
+                Check::new(
+                    printing && remaining.is_some_and(|r| r > 0 && r <= *minutes as i64),
+                    format!("{} finishing in {} minutes", name, remaining.unwrap_or(0)),
+                    file,
+                )
+            }
+            RuleKind::TempDeviation { degrees, seconds } => {
+                let (temp, target) = (state.nozzle_temp, state.nozzle_temp_target);
+                Check::new(
+                    printing && target > 0. && (temp - target).abs() > *degrees,
+                    format!("Nozzle temperature off target on {}", name),
+                    format!("Nozzle {:.0}°C, target {:.0}°C", temp, target),
+                )
+                .hold(Duration::from_secs(*seconds))
+            }
+            RuleKind::AmsHumidity { percent, index } => {
+                let ams = state.state_bambu.as_ref()?.ams.as_ref()?;
+                let (wet, body) = ams_humidity(ams, *percent, *index)?;
+                Check::new(wet, format!("AMS humidity high on {}", name), body)
+            }
+        };
+        Some(check)
+    }
+}
+
+/// the first unit over its threshold, or the first unit if none is.
+/// `humidity_raw` is a percentage, units without it only report the 1 - 5 level, 0 if unknown
+fn ams_humidity(ams: &AmsStatus, percent: f32, index: i64) -> Option<(bool, String)> {
+    let mut units = ams.units.values().collect::<Vec<_>>();
+    units.sort_by_key(|u| u.id);
+
+    let readings = units
+        .into_iter()
+        .map(|u| match u.humidity_raw {
+            Some(h) => (h > percent, format!("AMS {}: {:.0}%", u.id + 1, h)),
+            None => (
+                (1..=index).contains(&u.humidity),
+                format!("AMS {}: humidity level {}", u.id + 1, u.humidity),
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    readings
+        .iter()
+        .find(|(wet, _)| *wet)
+        .or(readings.first())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::bambu_status::AmsUnit;
+
+    fn ams(units: &[(i64, i64, Option<f32>)]) -> AmsStatus {
+        AmsStatus {
+            units: units
+                .iter()
+                .map(|&(id, humidity, humidity_raw)| {
+                    (
+                        id,
+                        AmsUnit {
+                            id,
+                            humidity,
+                            humidity_raw,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ams_humidity_percent() {
+        let (wet, body) = ams_humidity(&ams(&[(0, 4, Some(45.))]), 40., 2).unwrap();
+        assert!(wet);
+        assert_eq!(body, "AMS 1: 45%");
+
+        let (wet, _) = ams_humidity(&ams(&[(0, 1, Some(30.))]), 40., 2).unwrap();
+        assert!(
+            !wet,
+            "the percentage wins over the level when both are there"
+        );
+    }
+
+    #[test]
+    fn ams_humidity_falls_back_to_level() {
+        let units = ams(&[(0, 4, None), (1, 2, None)]);
+        let (wet, body) = ams_humidity(&units, 40., 2).unwrap();
+        assert!(wet);
+        assert_eq!(body, "AMS 2: humidity level 2");
+
+        let (wet, body) = ams_humidity(&ams(&[(0, 3, None)]), 40., 2).unwrap();
+        assert!(!wet);
+        assert_eq!(body, "AMS 1: humidity level 3");
+
+        /// no reading
+        let (wet, _) = ams_humidity(&ams(&[(0, 0, None)]), 40., 2).unwrap();
+        assert!(!wet);
+    }
+
+    #[test]
+    fn ams_humidity_no_units() {
+        assert_eq!(ams_humidity(&ams(&[]), 40., 2), None);
+    }
+}
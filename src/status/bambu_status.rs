@@ -5,7 +5,10 @@ use std::{collections::HashMap, time::Instant};
 
 use serde::{de, Deserialize, Serialize};
 
-use crate::conn_manager::conn_bambu::message::{PrintAms, PrintData, PrintVtTray, VirtualSlotItem};
+use crate::conn_manager::conn_bambu::{
+    hms::HmsMessage,
+    message::{PrintAms, PrintData, PrintVtTray, VirtualSlotItem},
+};
 
 use super::PrinterState;
 
@@ -81,9 +84,14 @@ pub struct PrinterStateBambu {
 
     pub current_file: Option<String>,
     pub subtask_id: Option<String>,
+    /// project name, the .3mf is usually named after it
+    pub subtask_name: Option<String>,
+    pub task_id: Option<String>,
     pub current_task_thumbnail_url: Option<String>,
     // pub gcode_state: Option<GcodeState>,
     pub print_error: Option<PrintError>,
+    /// active HMS warnings, the text is looked up by the manager
+    pub hms: Vec<HmsMessage>,
     pub print_percent: Option<i64>,
     pub eta: Option<chrono::DateTime<chrono::Local>>,
     pub is_sdcard_printing: Option<bool>,
@@ -167,6 +175,14 @@ impl PrinterStateBambu {
             self.subtask_id = Some(id.clone());
         }
 
+        if let Some(name) = report.subtask_name.as_ref() {
+            self.subtask_name = Some(name.clone());
+        }
+
+        if let Some(id) = report.task_id.as_ref() {
+            self.task_id = Some(id.clone());
+        }
+
         if let Some(p) = report.mc_percent {
             self.print_percent = Some(p);
         }
@@ -175,6 +191,11 @@ impl PrinterStateBambu {
             self.print_error = Some(PrintError::from_code(e));
         }
 
+        /// only in full reports, where an empty list means everything cleared
+        if let Some(hms) = report.hms.as_ref() {
+            self.hms = hms.iter().copied().map(HmsMessage::new).collect();
+        }
+
         if let Some(t) = report.mc_remaining_time {
             self.eta = Some(
                 chrono::Local::now()
@@ -342,6 +363,9 @@ impl PrinterStateBambu {
                     slots[i] = Some(AmsSlot {
                         material: slot.tray_type.clone().unwrap_or("Unknown".to_string()),
                         k: slot.k.unwrap_or(0.),
+                        tray_info_idx: slot.tray_info_idx.clone().filter(|s| !s.is_empty()),
+                        nozzle_temp_min: slot.nozzle_temp_min.as_ref().and_then(|t| t.parse().ok()),
+                        nozzle_temp_max: slot.nozzle_temp_max.as_ref().and_then(|t| t.parse().ok()),
                         color,
                     });
                 }
@@ -362,6 +386,7 @@ impl PrinterStateBambu {
                         id,
                         info,
                         humidity: unit.humidity.parse().unwrap_or(0),
+                        humidity_raw: unit.humidity_raw.as_ref().and_then(|h| h.parse().ok()),
                         temp: unit.temp.parse().unwrap_or(0.),
                         slots,
                     },
@@ -421,6 +446,21 @@ impl PrinterStateBambu {
         matches!(self.state, PrinterState::Error(_))
     }
 
+    /// the most severe HMS message, if there are any
+    pub fn worst_hms(&self) -> Option<&HmsMessage> {
+        self.hms.iter().max_by_key(|h| h.entry.severity())
+    }
+
+    /// identifies the current print for the plate preview, None when there isn't one
+    pub fn thumbnail_key(&self) -> Option<String> {
+        let name = self.subtask_name.as_deref().unwrap_or_default();
+        let file = self.current_file.as_deref().unwrap_or_default();
+        if name.is_empty() && file.is_empty() {
+            return None;
+        }
+        Some(format!("{}:{}", name, file))
+    }
+
     pub fn reset(&mut self) {
         *self = Self::default();
     }
@@ -481,6 +521,11 @@ impl PrintError {
             _ => PrintError::Unknown(code),
         }
     }
+
+    /// 0300400C, "The task was canceled."
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, PrintError::Unknown(50348044))
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -530,6 +575,8 @@ impl AmsCurrentSlot {
 pub struct AmsUnit {
     pub id: i64,
     pub humidity: i64,
+    /// percent
+    pub humidity_raw: Option<f32>,
     pub temp: f64,
     pub info: Option<i64>,
     pub slots: [Option<AmsSlot>; 4],
@@ -539,6 +586,9 @@ pub struct AmsUnit {
 pub struct AmsSlot {
     pub material: String,
     pub k: f64,
+    pub tray_info_idx: Option<String>,
+    pub nozzle_temp_min: Option<i64>,
+    pub nozzle_temp_max: Option<i64>,
     // pub color: [u8; 3],
     pub color: egui::Color32,
 }
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{bambu_status::PrinterStateBambu, GenericPrinterState};
+
+/// MARK: Sample
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Sample {
+    /// unix time, seconds
+    pub time: f64,
+    pub nozzle: f32,
+    pub nozzle_target: f32,
+    pub bed: f32,
+    pub bed_target: f32,
+    pub chamber: f32,
+    pub chamber_target: Option<f32>,
+    /// 0 - 100
+    pub progress: f32,
+}
+
+impl Sample {
+    fn now() -> f64 {
+        chrono::Utc::now().timestamp_millis() as f64 / 1000.
+    }
+
+    pub fn from_state(state: &GenericPrinterState) -> Self {
+        /// toolchangers and multi-extruder printers report per tool
+        let (nozzle, nozzle_target) = match state.current_tool {
+            Some(t) if state.nozzle_temps.contains_key(&t) => (
+                state.nozzle_temps[&t],
+                state.nozzle_temps_target.get(&t).copied().unwrap_or(0.),
+            ),
+            _ => (state.nozzle_temp, state.nozzle_temp_target),
+        };
+
+        Self {
+            time: Self::now(),
+            nozzle,
+            nozzle_target,
+            bed: state.bed_temp,
+            bed_target: state.bed_temp_target,
+            chamber: state.chamber_temp,
+            chamber_target: state.chamber_temp_target,
+            progress: state.progress,
+        }
+    }
+
+    /// bambu reports are more complete than the generic state, fall back to it when missing
+    pub fn from_bambu(bambu: &PrinterStateBambu, state: &GenericPrinterState) -> Self {
+        let mut out = Self::from_state(state);
+        let get = |v: Option<f64>, default: f32| v.map(|v| v as f32).unwrap_or(default);
+        out.nozzle = get(bambu.temp_nozzle, out.nozzle);
+        out.nozzle_target = get(bambu.temp_tgt_nozzle, out.nozzle_target);
+        out.bed = get(bambu.temp_bed, out.bed);
+        out.bed_target = get(bambu.temp_tgt_bed, out.bed_target);
+        out.chamber = get(bambu.temp_chamber, out.chamber);
+        if let Some(p) = bambu.print_percent {
+            out.progress = p as f32;
+        }
+        out
+    }
+}
+
+/// MARK: History
+/// ring buffer of recent samples for one printer,
+/// shared between clones so copying a GenericPrinterState stays cheap
+#[derive(Debug, Clone, Default)]
+pub struct History(Arc<RwLock<VecDeque<Sample>>>);
+
+impl History {
+    /// 2 hours at one sample every 2 seconds
+    pub const CAPACITY: usize = 3600;
+    /// seconds, updates arriving faster than this are dropped
+    pub const INTERVAL: f64 = 2.0;
+
+    /// returns false if the previous sample was too recent
+    pub fn push(&self, sample: Sample) -> bool {
+        let mut buf = self.0.write().unwrap();
+        if let Some(last) = buf.back() {
+            if sample.time - last.time < Self::INTERVAL {
+                return false;
+            }
+        }
+        if buf.len() >= Self::CAPACITY {
+            buf.pop_front();
+        }
+        buf.push_back(sample);
+        true
+    }
+
+    /// for restoring persisted samples, oldest first
+    pub fn extend(&self, samples: impl IntoIterator<Item = Sample>) {
+        let mut buf = self.0.write().unwrap();
+        buf.extend(samples);
+        buf.make_contiguous()
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        let excess = buf.len().saturating_sub(Self::CAPACITY);
+        buf.drain(..excess);
+    }
+
+    pub fn last(&self) -> Option<Sample> {
+        self.0.read().unwrap().back().copied()
+    }
+
+    /// samples newer than `since` (unix seconds)
+    pub fn since(&self, since: f64) -> Vec<Sample> {
+        let buf = self.0.read().unwrap();
+        let start = buf.partition_point(|s| s.time < since);
+        buf.range(start..).copied().collect()
+    }
+}
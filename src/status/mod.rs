@@ -1,4 +1,6 @@
 pub mod bambu_status;
+pub mod history;
+pub mod terminal;
 // pub mod bambu_status2;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
@@ -8,7 +10,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum PrinterState {
     Idle,
     Finished,
@@ -42,7 +44,7 @@ impl PrinterState {
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct GenericPrinterState {
     pub state: PrinterState,
     pub wifi_signal: Option<i32>,
@@ -56,20 +58,82 @@ pub struct GenericPrinterState {
     pub chamber_temp_target: Option<f32>,
     pub current_tool: Option<usize>,
     pub fan_speed: f32,
+    /// toolhead X, Y, Z in mm, if the printer reports it
+    pub position: Option<[f32; 3]>,
+    /// None if the printer doesn't report it
+    pub homed_axes: Option<HomedAxes>,
     pub layer: Option<(u32, u32)>,
     pub progress: f32,
+    #[serde(serialize_with = "serialize_secs")]
     pub time_printing: Option<chrono::Duration>,
+    #[serde(serialize_with = "serialize_secs")]
     pub time_remaining: Option<chrono::Duration>,
     pub current_file: Option<String>,
     // pub thumbnail_path: Option<String>,
     pub state_prusa: Option<crate::conn_manager::conn_prusa::prusa_local_types::PrusaStatus>,
+    /// not serialized, most of the report is already in the generic fields
+    #[serde(skip)]
     pub state_bambu: Option<bambu_status::PrinterStateBambu>,
+    #[serde(skip)]
+    pub history: history::History,
+    /// OctoPrint serial log
+    #[serde(skip)]
+    pub terminal: terminal::Terminal,
+    /// a print job is open in the job history, set by the manager
+    #[serde(skip)]
+    pub job_active: bool,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HomedAxes {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl HomedAxes {
+    /// Klipper's `toolhead.homed_axes`, e.g. "xyz" or "xy"
+    pub fn from_klipper(axes: &str) -> Self {
+        let axes = axes.to_lowercase();
+        Self {
+            x: axes.contains('x'),
+            y: axes.contains('y'),
+            z: axes.contains('z'),
+        }
+    }
+
+    pub fn all(&self) -> bool {
+        self.x && self.y && self.z
+    }
+}
+
+/// durations as whole seconds
+fn serialize_secs<S: Serializer>(
+    d: &Option<chrono::Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    d.map(|d| d.num_seconds()).serialize(serializer)
 }
 
 impl GenericPrinterState {
     pub fn is_error(&self) -> bool {
         matches!(self.state, PrinterState::Error(_))
     }
+
+    /// why jogging and homing are refused, if they are.
+    /// errors and unknown states can happen mid-print, e.g. a Bambu pause with a print error
+    pub fn motion_blocked(&self) -> Option<String> {
+        match &self.state {
+            PrinterState::Printing | PrinterState::Paused | PrinterState::Busy => Some(format!(
+                "can't move while {}",
+                self.state.to_text().to_lowercase()
+            )),
+            PrinterState::Error(_) | PrinterState::Unknown(_) if self.job_active => {
+                Some("can't move during a print".to_string())
+            }
+            _ => None,
+        }
+    }
 }
 
 impl GenericPrinterState {
@@ -81,6 +145,8 @@ impl GenericPrinterState {
     }
 
     pub fn update_bambu(&mut self, update: bambu_status::PrinterStateBambu) {
+        self.history
+            .push(history::Sample::from_bambu(&update, self));
         self.state_bambu = Some(update);
     }
 
@@ -88,6 +154,7 @@ impl GenericPrinterState {
         for u in update.0 {
             self._update(u);
         }
+        self.history.push(history::Sample::from_state(self));
     }
 
     fn _update(&mut self, update: PrinterStateUpdate) {
@@ -155,7 +222,15 @@ impl GenericPrinterState {
             // _ => tracing::warn!("GenericPrinterState::_update TODO: {:?}", update),
             PrinterStateUpdate::CurrentTool(tool) => self.current_tool = tool,
             PrinterStateUpdate::FanSetting(s) => self.fan_speed = s,
-            PrinterStateUpdate::Duration(_) => todo!(),
+            PrinterStateUpdate::Duration(time) => self.time_printing = Some(time),
+            PrinterStateUpdate::Position(pos) => self.position = Some(pos),
+            PrinterStateUpdate::HomedAxes(axes) => self.homed_axes = Some(axes),
+            /// anything older than what's already there is dropped
+            PrinterStateUpdate::TempHistory(samples) => {
+                for sample in samples {
+                    self.history.push(sample);
+                }
+            }
         }
     }
 
@@ -217,6 +292,11 @@ pub enum PrinterStateUpdate {
     WifiSignal(i32),
     CurrentTool(Option<usize>),
     FanSetting(f32),
+    /// X, Y, Z in mm
+    Position([f32; 3]),
+    HomedAxes(HomedAxes),
+    /// readings from before connecting, oldest first
+    TempHistory(Vec<history::Sample>),
 }
 
 #[derive(Debug, Default, Clone)]
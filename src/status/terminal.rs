@@ -0,0 +1,58 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+/// MARK: TerminalLine
+#[derive(Debug, Clone)]
+pub struct TerminalLine {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub text: String,
+    /// a message from the printer, otherwise a serial log line.
+    /// the log also contains the messages, along with the commands sent
+    pub message: bool,
+}
+
+impl TerminalLine {
+    pub fn new(text: String, message: bool) -> Self {
+        Self {
+            time: chrono::Local::now(),
+            text,
+            message,
+        }
+    }
+}
+
+/// MARK: Terminal
+/// recent serial log lines for one printer, shared between clones like History
+#[derive(Debug, Clone, Default)]
+pub struct Terminal(Arc<RwLock<VecDeque<TerminalLine>>>);
+
+impl Terminal {
+    pub const CAPACITY: usize = 2000;
+
+    pub fn extend(&self, lines: impl IntoIterator<Item = TerminalLine>) {
+        let mut buf = self.0.write().unwrap();
+        buf.extend(lines);
+        let excess = buf.len().saturating_sub(Self::CAPACITY);
+        buf.drain(..excess);
+    }
+
+    pub fn clear(&self) {
+        self.0.write().unwrap().clear();
+    }
+
+    /// either the printer's messages or the full log
+    pub fn lines(&self, messages: bool) -> Vec<TerminalLine> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|l| l.message == messages)
+            .cloned()
+            .collect()
+    }
+}
@@ -3,9 +3,16 @@ use tracing::{debug, error, info, trace, warn};
 
 use egui::{response, Color32, Layout, Pos2, Rect, RichText, Sense, Stroke, Vec2};
 
-use crate::status::bambu_status::{AmsCurrentSlot, AmsUnit};
+use crate::{
+    conn_manager::{conn_bambu::command::ChangeAMSFilamentSetting, WorkerCmd},
+    status::bambu_status::{AmsCurrentSlot, AmsUnit},
+};
+
+use super::{app::App, ui_types::AmsSlotEditor};
 
 /// pretend that the configuration will always be one (external spool or AMS HT) + 1 AMS
+///
+/// returns the (ams_id, tray_id) of a clicked slot
 // #[cfg(feature = "nope")]
 pub(super) fn paint_ams_h2d(
     ui: &mut egui::Ui,
@@ -14,7 +21,9 @@ pub(super) fn paint_ams_h2d(
     height: f32,
     // ams: &AmsStatus,
     bambu: &crate::status::bambu_status::PrinterStateBambu,
-) {
+) -> Option<(i64, i64)> {
+    let mut clicked = None;
+
     let layout = Layout::left_to_right(egui::Align::Center)
         .with_cross_justify(true)
         .with_main_justify(true)
@@ -32,20 +41,12 @@ pub(super) fn paint_ams_h2d(
 
     let Some(ams) = bambu.ams.as_ref() else {
         // warn!("AMS not found");
-        return;
+        return None;
     };
 
-    let external_left = bambu
-        .vir_slot
-        .as_ref()
-        .and_then(|v| v.get(0))
-        .map(|v| &v.tray_color);
+    let external_left = bambu.vir_slot.as_ref().and_then(|v| v.get(0));
 
-    let external_right = bambu
-        .vir_slot
-        .as_ref()
-        .and_then(|v| v.get(1))
-        .map(|v| &v.tray_color);
+    let external_right = bambu.vir_slot.as_ref().and_then(|v| v.get(1));
 
     let left_ams: Option<(&i64, &AmsUnit)> = {
         let mut out = None;
@@ -92,12 +93,12 @@ pub(super) fn paint_ams_h2d(
                     .outer_margin(0.)
                     .show(ui, |ui| {
                         let size = Vec2::new(ui.available_width(), height);
-                        let (response, painter) = ui.allocate_painter(size, Sense::hover());
+                        let (response, painter) = ui.allocate_painter(size, Sense::click());
 
                         match left_ams {
                             Some((_, unit)) => {
                                 // debug!("Left AMS: {:?}", unit);
-                                _draw_ams_h2d(
+                                if let Some(tray_id) = _draw_ams_h2d(
                                     ui,
                                     &response,
                                     &painter,
@@ -105,14 +106,23 @@ pub(super) fn paint_ams_h2d(
                                     unit,
                                     ams.current_tray,
                                     bambu.device.extruder.as_ref(),
-                                );
+                                ) {
+                                    clicked = Some((unit.id, tray_id));
+                                }
                             }
                             None => {
                                 match external_left {
-                                    Some(color) => {
-                                        _draw_external_spool_h2d(
-                                            ui, &response, &painter, bambu, color,
+                                    Some(slot) => {
+                                        let spool_clicked = _draw_external_spool_h2d(
+                                            ui,
+                                            &response,
+                                            &painter,
+                                            bambu,
+                                            &slot.tray_color,
                                         );
+                                        if spool_clicked {
+                                            clicked = external_spool_id(&slot.id);
+                                        }
                                     }
                                     None => {
                                         // debug!("No external spool found");
@@ -136,12 +146,12 @@ pub(super) fn paint_ams_h2d(
                         //     .debug_rect(ui.max_rect(), Color32::GREEN, "");
 
                         let size = Vec2::new(ui.available_width(), height);
-                        let (response, painter) = ui.allocate_painter(size, Sense::hover());
+                        let (response, painter) = ui.allocate_painter(size, Sense::click());
 
                         match right_ams {
                             Some((_, unit)) => {
                                 // debug!("Left AMS: {:?}", unit);
-                                _draw_ams_h2d(
+                                if let Some(tray_id) = _draw_ams_h2d(
                                     ui,
                                     &response,
                                     &painter,
@@ -149,14 +159,23 @@ pub(super) fn paint_ams_h2d(
                                     unit,
                                     ams.current_tray,
                                     bambu.device.extruder.as_ref(),
-                                );
+                                ) {
+                                    clicked = Some((unit.id, tray_id));
+                                }
                             }
                             None => {
                                 match external_right {
-                                    Some(color) => {
-                                        _draw_external_spool_h2d(
-                                            ui, &response, &painter, bambu, color,
+                                    Some(slot) => {
+                                        let spool_clicked = _draw_external_spool_h2d(
+                                            ui,
+                                            &response,
+                                            &painter,
+                                            bambu,
+                                            &slot.tray_color,
                                         );
+                                        if spool_clicked {
+                                            clicked = external_spool_id(&slot.id);
+                                        }
                                     }
                                     None => {
                                         // debug!("No external spool found");
@@ -173,6 +192,14 @@ pub(super) fn paint_ams_h2d(
     //     Color32::from_rgba_unmultiplied(255, 0, 0, 50),
     //     "",
     // );
+
+    clicked
+}
+
+/// vir_slot ids are the tray_id for the external spools, e.g. "254"
+fn external_spool_id(id: &str) -> Option<(i64, i64)> {
+    let tray_id = id.parse::<i64>().ok()?;
+    Some((ChangeAMSFilamentSetting::EXTERNAL_AMS_ID, tray_id))
 }
 
 const MARGIN_H: f32 = 2.;
@@ -184,7 +211,7 @@ fn _draw_external_spool_h2d(
     painter: &egui::Painter,
     bambu: &crate::status::bambu_status::PrinterStateBambu,
     color: &str,
-) {
+) -> bool {
     // ui.label("Ext");
 
     let rect = response.rect;
@@ -198,7 +225,7 @@ fn _draw_external_spool_h2d(
 
     let Ok(color) = Color32::from_hex(&format!("#{}", color)) else {
         error!("Invalid color: {}", color);
-        return;
+        return false;
     };
 
     // painter.debug_text(
@@ -231,6 +258,7 @@ fn _draw_external_spool_h2d(
     });
 
     // unimplemented!()
+    response.clicked()
 }
 
 fn _draw_ams_h2d(
@@ -241,11 +269,17 @@ fn _draw_ams_h2d(
     unit: &AmsUnit,
     current_tray: Option<AmsCurrentSlot>,
     extruder: Option<&crate::status::bambu_status::h2d_extruder::H2DExtruder>,
-) {
+) -> Option<i64> {
     let rect = response.rect;
 
     let border_color = ui.style().visuals.widgets.noninteractive.fg_stroke.color;
 
+    let click_pos = response
+        .clicked()
+        .then(|| response.interact_pointer_pos())
+        .flatten();
+    let mut clicked = None;
+
     for (i, slot) in unit.slots.iter().enumerate() {
         // center = margin + slot width / 2 + slot width * i
         let x = rect.left()
@@ -256,6 +290,10 @@ fn _draw_ams_h2d(
 
         let rect = Rect::from_center_size(Pos2::new(x, y), Vec2::new(SLOT_SIZE.0, SLOT_SIZE.1));
 
+        if click_pos.is_some_and(|p| rect.contains(p)) {
+            clicked = Some(i as i64);
+        }
+
         let is_current = match current_tray {
             Some(AmsCurrentSlot::Tray { ams_id, tray_id }) => {
                 unit.id == ams_id as i64 && i == tray_id as usize
@@ -311,6 +349,7 @@ fn _draw_ams_h2d(
         }
     }
 
+    clicked
     // unimplemented!()
 }
 
@@ -344,3 +383,192 @@ fn make_border_color(main_color: Color32) -> Color32 {
 
     color
 }
+
+/// MARK: Slot editor
+impl App {
+    pub(super) fn open_ams_editor(
+        &mut self,
+        id: &crate::config::printer_id::PrinterId,
+        ams_id: i64,
+        tray_id: i64,
+    ) {
+        let Some(status) = self.printer_states.get(id) else {
+            return;
+        };
+        let Some(bambu) = status.state_bambu.as_ref() else {
+            return;
+        };
+
+        /// start from the current tray contents, falling back to Generic PLA
+        let mut setting = ChangeAMSFilamentSetting {
+            ams_id,
+            tray_id,
+            tray_info_idx: "GFL99".to_string(),
+            tray_color: [255, 255, 255],
+            nozzle_temp_min: 190,
+            nozzle_temp_max: 240,
+            tray_type: "PLA".to_string(),
+        };
+
+        let (tray_info_idx, tray_type, color, temp_min, temp_max) =
+            if ams_id == ChangeAMSFilamentSetting::EXTERNAL_AMS_ID {
+                let Some(slot) = bambu
+                    .vir_slot
+                    .as_ref()
+                    .and_then(|v| v.iter().find(|s| s.id == tray_id.to_string()))
+                else {
+                    return;
+                };
+                (
+                    Some(slot.tray_info_idx.clone()),
+                    Some(slot.tray_type.clone()),
+                    Color32::from_hex(&format!("#{}", slot.tray_color)).ok(),
+                    slot.nozzle_temp_min.parse().ok(),
+                    slot.nozzle_temp_max.parse().ok(),
+                )
+            } else {
+                let slot = bambu
+                    .ams
+                    .as_ref()
+                    .and_then(|a| a.units.get(&ams_id))
+                    .and_then(|u| u.slots.get(tray_id as usize))
+                    .and_then(|s| s.as_ref());
+                match slot {
+                    Some(slot) => (
+                        slot.tray_info_idx.clone(),
+                        Some(slot.material.clone()),
+                        Some(slot.color),
+                        slot.nozzle_temp_min,
+                        slot.nozzle_temp_max,
+                    ),
+                    None => (None, None, None, None, None),
+                }
+            };
+
+        if let Some(t) = tray_info_idx.filter(|t| !t.is_empty()) {
+            setting.tray_info_idx = t;
+        }
+        if let Some(t) = tray_type.filter(|t| !t.is_empty()) {
+            setting.tray_type = t;
+        }
+        if let Some(c) = color {
+            setting.tray_color = [c.r(), c.g(), c.b()];
+        }
+        if let Some(t) = temp_min {
+            setting.nozzle_temp_min = t;
+        }
+        if let Some(t) = temp_max {
+            setting.nozzle_temp_max = t;
+        }
+
+        drop(status);
+        self.ams_editor = Some(AmsSlotEditor {
+            printer_id: id.clone(),
+            setting,
+        });
+    }
+
+    pub(super) fn show_ams_editor(&mut self, ctx: &egui::Context) {
+        use crate::conn_manager::conn_bambu::command::{FilamentPreset, FILAMENT_PRESETS};
+
+        let Some(editor) = self.ams_editor.as_mut() else {
+            return;
+        };
+
+        let title = if editor.setting.ams_id == ChangeAMSFilamentSetting::EXTERNAL_AMS_ID {
+            "Edit External Spool".to_string()
+        } else {
+            format!(
+                "Edit AMS {} Slot {}",
+                editor.setting.ams_id + 1,
+                editor.setting.tray_id + 1
+            )
+        };
+
+        let mut open = true;
+        let mut send = false;
+        let mut cancel = false;
+
+        egui::Window::new(title)
+            .id(egui::Id::new("ams_slot_editor"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let setting = &mut editor.setting;
+
+                egui::Grid::new("ams_slot_editor_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Filament");
+                        let selected = FilamentPreset::find(&setting.tray_info_idx)
+                            .map(|p| p.name.to_string())
+                            .unwrap_or_else(|| format!("Custom ({})", setting.tray_info_idx));
+                        egui::ComboBox::from_id_salt("ams_slot_editor_preset")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for preset in FILAMENT_PRESETS.iter() {
+                                    let resp = ui.selectable_label(
+                                        setting.tray_info_idx == preset.tray_info_idx,
+                                        preset.name,
+                                    );
+                                    if resp.clicked() {
+                                        setting.tray_info_idx = preset.tray_info_idx.to_string();
+                                        setting.tray_type = preset.tray_type.to_string();
+                                        setting.nozzle_temp_min = preset.nozzle_temp_min;
+                                        setting.nozzle_temp_max = preset.nozzle_temp_max;
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Type");
+                        ui.text_edit_singleline(&mut setting.tray_type);
+                        ui.end_row();
+
+                        ui.label("Color");
+                        ui.color_edit_button_srgb(&mut setting.tray_color);
+                        ui.end_row();
+
+                        ui.label("Nozzle Temp");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut setting.nozzle_temp_min)
+                                    .range(150..=350)
+                                    .suffix("°C"),
+                            );
+                            ui.label("-");
+                            ui.add(
+                                egui::DragValue::new(&mut setting.nozzle_temp_max)
+                                    .range(setting.nozzle_temp_min..=350)
+                                    .suffix("°C"),
+                            );
+                        });
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        send = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if send {
+            let editor = self.ams_editor.take().unwrap();
+            if let Err(e) = self.send_worker_cmd(
+                &editor.printer_id,
+                WorkerCmd::SetAmsFilament(editor.setting),
+            ) {
+                error!("error sending AMS setting: {:?}", e);
+            }
+        } else if cancel || !open {
+            self.ams_editor = None;
+        }
+    }
+}
@@ -11,12 +11,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     config::{printer_id::PrinterId, AppConfig},
-    conn_manager::{PrinterConnCmd, PrinterConnMsg},
+    conn_manager::{CommandId, PrinterConnCmd, PrinterConnMsg, WorkerCmd},
     status::GenericPrinterState,
     streaming::StreamCmd,
 };
 
-use super::ui_types::{AppOptions, GridLocation, PreviewType, Tab, ThumbnailMap, WebcamTexture};
+use super::ui_types::{
+    AmsSlotEditor, AppOptions, CommandStatus, ConfirmDialog, EventsView, FileBrowser, GraphsView,
+    GridLocation, MotionPanel, PreviewType, Tab, TerminalWindow, ThumbnailMap, WebcamTexture,
+};
 
 #[derive(Default, Deserialize, Serialize)]
 #[serde(default)]
@@ -56,6 +59,33 @@ pub struct App {
 
     #[serde(skip)]
     pub selected_stream: Option<PrinterId>,
+
+    /// printer to show in the fullscreen view next frame, set from the printer menu
+    #[serde(skip)]
+    pub open_controls: dashmap::DashSet<PrinterId>,
+
+    #[serde(skip)]
+    pub command_status: DashMap<PrinterId, CommandStatus>,
+
+    /// commands waiting for "are you sure", shown one at a time
+    #[serde(skip)]
+    pub confirm: DashMap<PrinterId, ConfirmDialog>,
+
+    #[serde(skip)]
+    pub ams_editor: Option<AmsSlotEditor>,
+
+    #[serde(skip)]
+    pub file_browser: Option<FileBrowser>,
+
+    /// open terminal windows, opened from the printer menu
+    #[serde(skip)]
+    pub terminals: DashMap<PrinterId, TerminalWindow>,
+
+    #[serde(skip)]
+    pub events: EventsView,
+
+    pub graphs: GraphsView,
+    pub motion_panel: MotionPanel,
     // #[serde(skip)]
     // pub printer_config_page: PrinterConfigPage,
     pub options: AppOptions,
@@ -99,6 +129,11 @@ impl App {
         out.msg_rx = Some(msg_rx);
         out.stream_cmd_tx = Some(stream_cmd_tx);
 
+        let (wake, bed) = out.options.times_opt;
+        if let Err(e) = out.send_cmd(PrinterConnCmd::SetQuietHours(wake, bed)) {
+            error!("failed to send quiet hours: {:?}", e);
+        }
+
         out.unplaced_printers = out.config.printer_ids();
 
         debug!("printer_order: {:?}", out.printer_order);
@@ -162,6 +197,36 @@ impl App {
             PrinterConnMsg::NewThumbnail(id, file, img) => {
                 self.thumbnails.insert(id, (file, img));
             }
+            PrinterConnMsg::CommandResult(id, cmd_id, result) => {
+                /// ignore results for commands that have since been replaced
+                if let Some(mut status) = self.command_status.get_mut(&id) {
+                    if status.id == cmd_id {
+                        status.result = Some(result);
+                        status.time = std::time::Instant::now();
+                    }
+                }
+            }
+            PrinterConnMsg::Events(Ok(page)) => {
+                /// replies to older queries can arrive while typing
+                if Some(&page.query) == self.events.last_query.as_ref() {
+                    self.events.loading = false;
+                    self.events.error = None;
+                    self.events.results = Some(page);
+                }
+            }
+            PrinterConnMsg::Events(Err(e)) => {
+                self.events.loading = false;
+                self.events.error = Some(e);
+            }
+            PrinterConnMsg::EventsExported(path, result) => {
+                self.events.export_status = Some(match result {
+                    Ok(n) => format!("Exported {} events to {}", n, path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            PrinterConnMsg::Files(id, result) => {
+                self.handle_file_result(id, result);
+            }
             _ => {
                 warn!("unhandled message: {:?}", msg);
             }
@@ -174,6 +239,14 @@ impl App {
         Ok(())
     }
 
+    /// send a command to a printer and track its result
+    pub fn send_worker_cmd(&self, id: &PrinterId, cmd: WorkerCmd) -> Result<()> {
+        let cmd_id = CommandId::next();
+        self.command_status
+            .insert(id.clone(), CommandStatus::new(cmd_id, cmd.clone()));
+        self.send_cmd(PrinterConnCmd::WorkerCmd(id.clone(), cmd_id, cmd))
+    }
+
     pub fn send_stream_cmd(&self, cmd: StreamCmd) -> Result<()> {
         let tx = self.stream_cmd_tx.as_ref().unwrap();
         tx.send(cmd)?;
@@ -197,8 +270,9 @@ impl eframe::App for App {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.current_tab, Tab::Dashboard, "Dashboard");
+                ui.selectable_value(&mut self.current_tab, Tab::Events, "Events");
                 // ui.selectable_value(&mut self.current_tab, Tab::Graphs, "Quick View");
-                // ui.selectable_value(&mut self.current_tab, Tab::Graphs, "Graphs");
+                ui.selectable_value(&mut self.current_tab, Tab::Graphs, "Graphs");
                 // ui.selectable_value(&mut self.current_tab, Tab::Printers, "Printers");
                 // ui.selectable_value(&mut self.current_tab, Tab::Projects, "Projects");
                 ui.selectable_value(&mut self.current_tab, Tab::Options, "Options");
@@ -288,6 +362,13 @@ impl eframe::App for App {
             });
         });
 
+        /// requested from a printer's menu, which can't borrow self mutably
+        if let Some(id) = self.open_controls.iter().next().map(|id| id.clone()) {
+            self.open_controls.clear();
+            self.selected_stream = Some(id);
+            self.current_tab = Tab::Dashboard;
+        }
+
         match self.current_tab {
             Tab::Dashboard => {
                 if let Some(id) = self.selected_stream.as_ref() {
@@ -306,16 +387,20 @@ impl eframe::App for App {
                     });
                 }
             }
+            Tab::Events => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    self.show_events(ui);
+                });
+            }
             Tab::QuickView => {
                 // egui::CentralPanel::default().show(ctx, |ui| {
                 //     self.show_quick_view(ui);
                 // });
             }
             Tab::Graphs => {
-                // egui::CentralPanel::default().show(ctx, |ui| {
-                //     self.show_graphs(ui);
-                // });
-                unimplemented!()
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    self.show_graphs(ui);
+                });
             }
             Tab::Projects => {
                 // self.show_project_view(ctx);
@@ -335,13 +420,35 @@ impl eframe::App for App {
                 // });
             }
         }
+
+        self.show_ams_editor(ctx);
+        self.show_file_browser(ctx);
+        self.show_terminals(ctx);
+        self.show_confirm(ctx);
     }
 }
 
 impl App {
     pub fn show_fullscreen_printer(&mut self, ui: &mut egui::Ui, id: PrinterId) {
-        let Some(entry) = self.webcam_textures.get(&id) else {
-            self.selected_stream = None;
+        let mut close = false;
+        egui::SidePanel::right("motion_panel")
+            .resizable(false)
+            .show_inside(ui, |ui| {
+                if ui.button("Back").clicked() {
+                    close = true;
+                }
+                self.motion_panel(ui, &id);
+            });
+
+        let textures = self.webcam_textures.clone();
+        /// opened from the menu, for printers without a webcam
+        let Some(entry) = textures.get(&id) else {
+            if close {
+                self.selected_stream = None;
+            }
+            ui.centered_and_justified(|ui| {
+                ui.label("No webcam");
+            });
             return;
         };
         if !entry.enabled.load(std::sync::atomic::Ordering::SeqCst) {
@@ -363,7 +470,7 @@ impl App {
 
         let resp = ui.add(img);
 
-        if resp.clicked() {
+        if resp.clicked() || close {
             self.selected_stream = None;
             entry
                 .active
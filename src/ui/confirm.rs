@@ -0,0 +1,87 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::{Color32, RichText};
+
+use crate::{config::printer_id::PrinterId, conn_manager::PrinterConnCmd};
+
+use super::{
+    app::App,
+    ui_types::{ConfirmCmd, ConfirmDialog},
+};
+
+/// MARK: Confirm
+impl App {
+    /// asks before sending `cmd`, replacing any earlier question for the same printer
+    pub(super) fn confirm(&self, id: &PrinterId, text: &str, button: &str, cmd: ConfirmCmd) {
+        self.confirm.insert(
+            id.clone(),
+            ConfirmDialog {
+                text: text.to_string(),
+                button: button.to_string(),
+                cmd,
+            },
+        );
+    }
+
+    pub(super) fn show_confirm(&mut self, ctx: &egui::Context) {
+        let Some((id, dialog)) = self
+            .confirm
+            .iter()
+            .next()
+            .map(|d| (d.key().clone(), d.value().clone()))
+        else {
+            return;
+        };
+        let name = self
+            .config
+            .get_printer(&id)
+            .map(|p| p.name_blocking())
+            .unwrap_or_default();
+
+        let mut confirmed = false;
+        let mut dismissed = false;
+
+        let resp = egui::Modal::new(egui::Id::new(("confirm", id.inner()))).show(ctx, |ui| {
+            ui.set_width(260.);
+            ui.label(RichText::new(name).strong());
+            ui.label(&dialog.text);
+            ui.separator();
+            ui.horizontal(|ui| {
+                let button = egui::Button::new(RichText::new(&dialog.button).color(Color32::RED));
+                if ui.add(button).clicked() {
+                    confirmed = true;
+                }
+                if ui.button("Back").clicked() {
+                    dismissed = true;
+                }
+            });
+        });
+
+        /// escape or a click outside
+        if resp.should_close() {
+            dismissed = true;
+        }
+
+        if confirmed {
+            let res = match dialog.cmd {
+                ConfirmCmd::Worker(cmd) => self.send_worker_cmd(&id, cmd),
+                ConfirmCmd::Files(cmd) => {
+                    if let Some(browser) = self.file_browser.as_mut() {
+                        if browser.printer_id == id {
+                            browser.loading = true;
+                            browser.status = None;
+                        }
+                    }
+                    self.send_cmd(PrinterConnCmd::Files(id.clone(), cmd))
+                }
+            };
+            if let Err(e) = res {
+                error!("error sending command: {:?}", e);
+            }
+        }
+        if confirmed || dismissed {
+            self.confirm.remove(&id);
+        }
+    }
+}
@@ -180,9 +180,15 @@ impl App {
                 };
 
                 self.show_printer_prusa(ui, pos, &printer);
-            } // PrinterConfig::Octoprint(id, print) => {
-              //     todo!();
-              // }
+            }
+            PrinterConfig::Octoprint(id, printer) => {
+                let Ok(printer) = printer.try_read() else {
+                    warn!("printer locked");
+                    return;
+                };
+
+                self.show_printer_octoprint(ui, pos, &printer);
+            }
         }
     }
 
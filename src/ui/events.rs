@@ -0,0 +1,196 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use std::collections::HashMap;
+
+use egui::{Color32, RichText};
+use egui_phosphor::fill;
+
+use crate::{
+    config::printer_id::PrinterId, conn_manager::PrinterConnCmd,
+    error_logging::error_db::ExportFormat,
+};
+
+use super::{app::App, ui_types::EventRange};
+
+/// MARK: Events tab
+impl App {
+    pub fn show_events(&mut self, ui: &mut egui::Ui) {
+        if self.events.last_query.is_none() {
+            self.refresh_events();
+        }
+
+        let mut printers = self
+            .config
+            .printers()
+            .iter()
+            .map(|p| (p.id(), p.name_blocking()))
+            .collect::<Vec<_>>();
+        printers.sort_by(|a, b| a.1.cmp(&b.1));
+        let names = printers
+            .iter()
+            .cloned()
+            .collect::<HashMap<PrinterId, String>>();
+
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            let selected = self
+                .events
+                .printer
+                .as_ref()
+                .and_then(|id| names.get(id).cloned())
+                .unwrap_or("All printers".to_string());
+            egui::ComboBox::from_id_salt("events_printer")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut self.events.printer, None, "All printers")
+                        .changed();
+                    for (id, name) in printers.iter() {
+                        changed |= ui
+                            .selectable_value(&mut self.events.printer, Some(id.clone()), name)
+                            .changed();
+                    }
+                });
+
+            egui::ComboBox::from_id_salt("events_range")
+                .selected_text(self.events.range.label())
+                .show_ui(ui, |ui| {
+                    for range in EventRange::ALL {
+                        changed |= ui
+                            .selectable_value(&mut self.events.range, range, range.label())
+                            .changed();
+                    }
+                });
+
+            ui.label(fill::MAGNIFYING_GLASS);
+            changed |= ui.text_edit_singleline(&mut self.events.text).changed();
+
+            if ui.button(fill::ARROWS_CLOCKWISE).clicked() {
+                changed = true;
+            }
+
+            if self.events.loading {
+                ui.spinner();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let prev = self.events.export_format;
+            egui::ComboBox::from_id_salt("events_export_format")
+                .selected_text(format!("{:?}", self.events.export_format))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.events.export_format, ExportFormat::Csv, "Csv");
+                    ui.selectable_value(&mut self.events.export_format, ExportFormat::Json, "Json");
+                });
+            if prev != self.events.export_format {
+                let mut path = std::path::PathBuf::from(&self.events.export_path);
+                path.set_extension(self.events.export_format.extension());
+                self.events.export_path = path.display().to_string();
+            }
+
+            ui.text_edit_singleline(&mut self.events.export_path);
+
+            if ui.button("Export").clicked() {
+                let cmd = PrinterConnCmd::ExportEvents(
+                    self.events.query(),
+                    self.events.export_format,
+                    self.events.export_path.clone().into(),
+                );
+                if let Err(e) = self.send_cmd(cmd) {
+                    error!("failed to send export: {:?}", e);
+                }
+                self.events.export_status = None;
+            }
+
+            if let Some(status) = self.events.export_status.as_ref() {
+                ui.label(status);
+            }
+        });
+
+        if changed {
+            self.events.page = 0;
+            self.refresh_events();
+        }
+
+        ui.separator();
+
+        if let Some(e) = self.events.error.as_ref() {
+            ui.label(RichText::new(e).color(Color32::RED));
+        }
+
+        let Some(page) = self.events.results.as_ref() else {
+            return;
+        };
+
+        let num_pages = ((page.total + self.events.page_size - 1) / self.events.page_size).max(1);
+        let mut new_page = None;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.events.page > 0, egui::Button::new(fill::CARET_LEFT))
+                .clicked()
+            {
+                new_page = Some(self.events.page - 1);
+            }
+            ui.label(format!(
+                "Page {} of {} ({} events)",
+                self.events.page + 1,
+                num_pages,
+                page.total
+            ));
+            if ui
+                .add_enabled(
+                    self.events.page + 1 < num_pages,
+                    egui::Button::new(fill::CARET_RIGHT),
+                )
+                .clicked()
+            {
+                new_page = Some(self.events.page + 1);
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                egui::Grid::new("events_grid")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Time").strong());
+                        ui.label(RichText::new("Printer").strong());
+                        ui.label(RichText::new("Message").strong());
+                        ui.label(RichText::new("Description").strong());
+                        ui.end_row();
+
+                        for e in page.entries.iter() {
+                            let time = e.timestamp.and_utc().with_timezone(&chrono::Local);
+                            ui.label(time.format("%Y-%m-%d %H:%M").to_string());
+
+                            let id: PrinterId = e.printer_id.clone().into();
+                            ui.label(names.get(&id).unwrap_or(&e.printer_id));
+
+                            ui.label(&e.message);
+                            ui.label(e.description.as_deref().unwrap_or(""));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(p) = new_page {
+            self.events.page = p;
+            self.refresh_events();
+        }
+    }
+
+    fn refresh_events(&mut self) {
+        let query = self.events.query();
+        if let Err(e) = self.send_cmd(PrinterConnCmd::QueryEvents(query.clone())) {
+            error!("failed to query events: {:?}", e);
+            return;
+        }
+        self.events.last_query = Some(query);
+        self.events.loading = true;
+    }
+}
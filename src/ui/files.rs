@@ -0,0 +1,318 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::{Color32, RichText};
+
+use crate::{
+    config::{printer_config::PrinterType, printer_id::PrinterId},
+    conn_manager::{
+        conn_bambu::command::ProjectFile,
+        files::{FileCmd, FileResult},
+        PrinterConnCmd, WorkerCmd,
+    },
+};
+
+use super::{
+    app::App,
+    ui_types::{ConfirmCmd, FileBrowser},
+};
+
+/// MARK: File browser
+impl App {
+    /// the window opens once the listing arrives
+    pub(super) fn open_file_browser(&self, id: &PrinterId) {
+        if let Err(e) = self.send_cmd(PrinterConnCmd::Files(
+            id.clone(),
+            FileCmd::List("/".to_string()),
+        )) {
+            error!("error listing files: {:?}", e);
+        }
+    }
+
+    pub(super) fn handle_file_result(
+        &mut self,
+        id: PrinterId,
+        result: std::result::Result<FileResult, String>,
+    ) {
+        /// a listing for another printer replaces the window
+        if self.file_browser.as_ref().map(|b| &b.printer_id) != Some(&id) {
+            if !matches!(result, Ok(FileResult::Listing(..)) | Err(_)) {
+                debug!("file result for closed browser: {:?}", result);
+                return;
+            }
+            self.file_browser = Some(FileBrowser::new(id.clone(), "/"));
+        }
+        let Some(browser) = self.file_browser.as_mut() else {
+            return;
+        };
+
+        browser.loading = false;
+        let refresh = match result {
+            Ok(FileResult::Listing(dir, files)) => {
+                browser.dir = dir;
+                browser.files = files;
+                false
+            }
+            Ok(FileResult::Uploaded(path)) => {
+                browser.status = Some(format!("Uploaded {}", path));
+                true
+            }
+            Ok(FileResult::Downloaded(path)) => {
+                browser.status = Some(format!("Saved to {}", path.display()));
+                false
+            }
+            Ok(FileResult::Deleted(path)) => {
+                browser.status = Some(format!("Deleted {}", path));
+                true
+            }
+            Err(e) => {
+                browser.status = Some(format!("Error: {}", e));
+                false
+            }
+        };
+
+        if refresh {
+            browser.loading = true;
+            let cmd = PrinterConnCmd::Files(id, FileCmd::List(browser.dir.clone()));
+            if let Err(e) = self.send_cmd(cmd) {
+                error!("error listing files: {:?}", e);
+            }
+        }
+    }
+
+    pub(super) fn show_file_browser(&mut self, ctx: &egui::Context) {
+        let Some(browser) = self.file_browser.as_mut() else {
+            return;
+        };
+
+        let Some(printer) = self.config.get_printer(&browser.printer_id) else {
+            self.file_browser = None;
+            return;
+        };
+        let name = printer.name_blocking();
+        /// Bambu prints need plate and AMS options first
+        let print_options = printer.printer_type() == PrinterType::Bambu;
+
+        let mut open = true;
+        let mut cmds: Vec<FileCmd> = vec![];
+        let mut print = None;
+        let mut print_file = None;
+        let mut cancel_print = false;
+        let mut delete = None;
+
+        egui::Window::new(format!("Files: {}", name))
+            .id(egui::Id::new("file_browser"))
+            .collapsible(false)
+            .default_width(420.)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let parent = browser
+                        .dir
+                        .trim_end_matches('/')
+                        .rsplit_once('/')
+                        .map(|(p, _)| if p.is_empty() { "/" } else { p });
+                    if ui
+                        .add_enabled(parent.is_some(), egui::Button::new("⬆"))
+                        .clicked()
+                    {
+                        cmds.push(FileCmd::List(parent.unwrap_or("/").to_string()));
+                    }
+                    if ui.button("⟳").clicked() {
+                        cmds.push(FileCmd::List(browser.dir.clone()));
+                    }
+                    ui.label(RichText::new(&browser.dir).strong());
+                    if browser.loading {
+                        ui.spinner();
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        egui::Grid::new("file_browser_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for file in browser.files.iter() {
+                                    if file.is_dir {
+                                        if ui.link(format!("📁 {}", file.name())).clicked() {
+                                            cmds.push(FileCmd::List(file.path.clone()));
+                                        }
+                                        ui.label("");
+                                    } else {
+                                        ui.label(file.name());
+                                        ui.label(file.size.map(format_size).unwrap_or_default());
+                                    }
+
+                                    ui.horizontal(|ui| {
+                                        if file.is_dir {
+                                            return;
+                                        }
+                                        if file.printable && ui.button("Print").clicked() {
+                                            if print_options {
+                                                browser.print = Some((
+                                                    ProjectFile::new(&file.path),
+                                                    String::new(),
+                                                ));
+                                            } else {
+                                                print_file = Some(file.path.clone());
+                                            }
+                                        }
+                                        if ui.button("Download").clicked() {
+                                            let local = std::path::Path::new(&browser.download_dir)
+                                                .join(file.name());
+                                            cmds.push(FileCmd::Download(file.path.clone(), local));
+                                        }
+                                        if ui.button("Delete").clicked() {
+                                            delete = Some(file.clone());
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                /// MARK: print options
+                if let Some((file, ams_mapping)) = browser.print.as_mut() {
+                    ui.separator();
+                    ui.label(RichText::new(format!("Print {}", file.subtask_name())).strong());
+
+                    let mapping = parse_ams_mapping(ams_mapping);
+                    egui::Grid::new("file_browser_print_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Plate");
+                            ui.add(egui::DragValue::new(&mut file.plate).range(1..=64));
+                            ui.end_row();
+
+                            ui.label("AMS mapping");
+                            ui.text_edit_singleline(ams_mapping).on_hover_text(
+                                "AMS tray for each filament, e.g. \"0, 2\" \
+                                     for AMS 1 slots 1 and 3, empty for the external spool",
+                            );
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.checkbox(&mut file.bed_leveling, "Bed leveling");
+                            ui.end_row();
+
+                            ui.label("");
+                            ui.checkbox(&mut file.timelapse, "Timelapse");
+                            ui.end_row();
+                        });
+
+                    if let Err(e) = &mapping {
+                        ui.label(RichText::new(e).color(Color32::RED));
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(mapping.is_ok(), egui::Button::new("Start Print"))
+                            .clicked()
+                        {
+                            let mut file = file.clone();
+                            file.ams_mapping = mapping.unwrap_or_default();
+                            print = Some(file);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel_print = true;
+                        }
+                    });
+                }
+
+                ui.separator();
+
+                egui::Grid::new("file_browser_transfer_grid")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        ui.label("Upload");
+                        ui.text_edit_singleline(&mut browser.upload_path)
+                            .on_hover_text("local file to upload into this folder");
+                        if ui
+                            .add_enabled(
+                                !browser.upload_path.is_empty(),
+                                egui::Button::new("Upload"),
+                            )
+                            .clicked()
+                        {
+                            cmds.push(FileCmd::Upload(
+                                browser.upload_path.clone().into(),
+                                browser.dir.clone(),
+                            ));
+                        }
+                        ui.end_row();
+
+                        ui.label("Save to");
+                        ui.text_edit_singleline(&mut browser.download_dir);
+                        ui.end_row();
+                    });
+
+                if let Some(status) = &browser.status {
+                    ui.label(status);
+                }
+            });
+
+        if !open {
+            self.file_browser = None;
+            return;
+        }
+
+        let id = browser.printer_id.clone();
+        if !cmds.is_empty() {
+            browser.loading = true;
+            browser.status = None;
+        }
+        if print.is_some() || cancel_print {
+            browser.print = None;
+        }
+
+        if let Some(file) = delete {
+            self.confirm(
+                &id,
+                &format!("Delete {} from the printer?", file.name()),
+                "Delete",
+                ConfirmCmd::Files(FileCmd::Delete(file.path)),
+            );
+        }
+
+        for cmd in cmds {
+            if let Err(e) = self.send_cmd(PrinterConnCmd::Files(id.clone(), cmd)) {
+                error!("error sending file command: {:?}", e);
+            }
+        }
+        let cmd = match (print, print_file) {
+            (Some(file), _) => Some(WorkerCmd::PrintProjectFile(file)),
+            (None, Some(path)) => Some(WorkerCmd::PrintFile(path)),
+            (None, None) => None,
+        };
+        if let Some(cmd) = cmd {
+            if let Err(e) = self.send_worker_cmd(&id, cmd) {
+                error!("error starting print: {:?}", e);
+            }
+        }
+    }
+}
+
+/// "0, 2" -> [0, 2]
+fn parse_ams_mapping(s: &str) -> std::result::Result<Vec<i64>, String> {
+    s.split(',')
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.parse::<i64>()
+                .map_err(|_| format!("bad AMS tray: {:?}", t))
+        })
+        .collect()
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
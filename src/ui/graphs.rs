@@ -0,0 +1,118 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::Color32;
+use egui_plot::{Legend, Line, LineStyle, Plot, PlotPoints};
+
+use crate::{config::printer_id::PrinterId, status::history::Sample};
+
+use super::app::App;
+
+const COLOR_NOZZLE: Color32 = Color32::from_rgb(230, 120, 60);
+const COLOR_BED: Color32 = Color32::from_rgb(80, 140, 230);
+const COLOR_CHAMBER: Color32 = Color32::from_rgb(120, 190, 110);
+
+/// MARK: Graphs tab
+impl App {
+    pub fn show_graphs(&mut self, ui: &mut egui::Ui) {
+        let mut printers = self
+            .config
+            .printers()
+            .iter()
+            .map(|p| (p.id(), p.name_blocking()))
+            .collect::<Vec<_>>();
+        printers.sort_by(|a, b| a.1.cmp(&b.1));
+
+        if self.graphs.printer.is_none() {
+            self.graphs.printer = printers.first().map(|(id, _)| id.clone());
+        }
+        let Some(id) = self.graphs.printer.clone() else {
+            ui.label("No printers");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let selected = printers
+                .iter()
+                .find(|(p, _)| *p == id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default();
+            egui::ComboBox::from_id_salt("graphs_printer")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (p, name) in printers.iter() {
+                        ui.selectable_value(&mut self.graphs.printer, Some(p.clone()), name);
+                    }
+                });
+
+            for mins in [10, 30, 60, 120] {
+                ui.selectable_value(&mut self.graphs.window_mins, mins, format!("{} min", mins));
+            }
+        });
+
+        let since = chrono::Utc::now().timestamp() as f64 - self.graphs.window_mins as f64 * 60.;
+        let samples = self
+            .printer_states
+            .get(&id)
+            .map(|s| s.history.since(since))
+            .unwrap_or_default();
+
+        if samples.is_empty() {
+            ui.label("No data yet");
+            return;
+        }
+
+        let points = |f: &dyn Fn(&Sample) -> Option<f32>| {
+            samples
+                .iter()
+                .filter_map(|s| f(s).map(|v| [s.time, v as f64]))
+                .collect::<PlotPoints>()
+        };
+
+        let show_chamber = samples.iter().any(|s| s.chamber > 0.);
+
+        Plot::new(("temp_graph", &id))
+            .legend(Legend::default())
+            .include_y(0.)
+            .y_axis_label("°C")
+            .x_axis_formatter(|mark, _| {
+                chrono::DateTime::from_timestamp(mark.value as i64, 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                    .unwrap_or_default()
+            })
+            .label_formatter(|name, p| {
+                let time = chrono::DateTime::from_timestamp(p.x as i64, 0)
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                format!("{}\n{}\n{:.1}°C", name, time, p.y)
+            })
+            .show(ui, |plot| {
+                plot.line(Line::new("Nozzle", points(&|s| Some(s.nozzle))).color(COLOR_NOZZLE));
+                plot.line(
+                    Line::new("Nozzle Target", points(&|s| Some(s.nozzle_target)))
+                        .color(COLOR_NOZZLE)
+                        .style(LineStyle::dashed_loose()),
+                );
+                plot.line(Line::new("Bed", points(&|s| Some(s.bed))).color(COLOR_BED));
+                plot.line(
+                    Line::new("Bed Target", points(&|s| Some(s.bed_target)))
+                        .color(COLOR_BED)
+                        .style(LineStyle::dashed_loose()),
+                );
+                if show_chamber {
+                    plot.line(
+                        Line::new("Chamber", points(&|s| Some(s.chamber))).color(COLOR_CHAMBER),
+                    );
+                    plot.line(
+                        Line::new("Chamber Target", points(&|s| s.chamber_target))
+                            .color(COLOR_CHAMBER)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+            });
+    }
+}
@@ -151,6 +151,9 @@ pub fn printer_type_icon(ui: &mut egui::Ui, size: f32, printer_type: PrinterType
         PrinterType::Prusa => {
             egui::include_image!("../../assets/icons/prusa_icon.png")
         }
+        PrinterType::Octoprint => {
+            egui::include_image!("../../assets/icons/octoprint_icon.svg")
+        }
     };
     let icon = egui::Image::new(src)
         .fit_to_exact_size(Vec2::new(size, size))
@@ -1,18 +1,24 @@
+mod ams;
 pub mod app;
+mod confirm;
 pub mod dashboard;
+pub mod events;
+mod files;
+pub mod graphs;
 pub mod icons;
 pub mod launch_error;
+mod motion;
 pub mod options;
+pub mod printer_header;
+pub mod quick_view;
+mod terminal;
 pub mod ui_types;
 pub mod ui_utils;
 pub mod video_player;
 pub mod widget_bambu;
-pub mod widget_klipper;
-// pub mod widget_octo;
-mod ams;
-pub mod printer_header;
-pub mod quick_view;
 pub mod widget_bambu_v2;
+pub mod widget_klipper;
+pub mod widget_octoprint;
 pub mod widget_prusa;
 
 // pub const PRINTER_WIDGET_SIZE: (f32, f32) = (280., 370.);
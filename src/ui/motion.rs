@@ -0,0 +1,153 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::{Color32, RichText, Vec2};
+
+use crate::{
+    config::printer_id::PrinterId,
+    conn_manager::{motion::MotionCmd, WorkerCmd},
+};
+
+use super::{app::App, ui_types::MotionPanel};
+
+/// MARK: Motion
+impl App {
+    /// jog, home and position, next to the fullscreen webcam
+    pub(super) fn motion_panel(&mut self, ui: &mut egui::Ui, id: &PrinterId) {
+        let Some((blocked, position, homed)) = self
+            .printer_states
+            .get(id)
+            .map(|s| (s.motion_blocked(), s.position, s.homed_axes))
+        else {
+            return;
+        };
+
+        /// the manager refuses these too, this just explains why
+        let idle = blocked.is_none();
+
+        let mut cmd = None;
+
+        ui.add_space(4.);
+        ui.label(RichText::new("Motion").strong());
+        ui.separator();
+
+        /// position, colored by whether the axis is homed
+        egui::Grid::new("motion_position")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let axes = [
+                    ("X", homed.map(|h| h.x)),
+                    ("Y", homed.map(|h| h.y)),
+                    ("Z", homed.map(|h| h.z)),
+                ];
+                for (i, (axis, homed)) in axes.into_iter().enumerate() {
+                    let color = match homed {
+                        Some(true) => Color32::GREEN,
+                        Some(false) => Color32::YELLOW,
+                        None => ui.style().visuals.text_color(),
+                    };
+                    ui.label(RichText::new(axis).strong().color(color))
+                        .on_hover_text(match homed {
+                            Some(true) => "Homed",
+                            Some(false) => "Not homed",
+                            None => "Homing state unknown",
+                        });
+                    let pos = match position {
+                        Some(pos) => format!("{:.2}", pos[i]),
+                        None => "--".to_string(),
+                    };
+                    ui.label(RichText::new(pos).monospace());
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+
+        ui.add_enabled_ui(idle, |ui| {
+            ui.horizontal(|ui| {
+                for step in MotionPanel::STEPS {
+                    ui.selectable_value(&mut self.motion_panel.step, step, format!("{}", step));
+                }
+            });
+
+            let step = self.motion_panel.step;
+            let size = Vec2::splat(32.);
+            let button =
+                |ui: &mut egui::Ui, text: &str| ui.add_sized(size, egui::Button::new(text));
+
+            ui.horizontal(|ui| {
+                /// XY
+                egui::Grid::new("motion_jog_xy")
+                    .spacing(Vec2::splat(2.))
+                    .show(ui, |ui| {
+                        ui.label("");
+                        if button(ui, "Y+").clicked() {
+                            cmd = Some(MotionCmd::jog_y(step));
+                        }
+                        ui.label("");
+                        ui.end_row();
+
+                        if button(ui, "X-").clicked() {
+                            cmd = Some(MotionCmd::jog_x(-step));
+                        }
+                        if button(ui, "⌂").on_hover_text("Home X and Y").clicked() {
+                            cmd = Some(MotionCmd::Home {
+                                x: true,
+                                y: true,
+                                z: false,
+                            });
+                        }
+                        if button(ui, "X+").clicked() {
+                            cmd = Some(MotionCmd::jog_x(step));
+                        }
+                        ui.end_row();
+
+                        ui.label("");
+                        if button(ui, "Y-").clicked() {
+                            cmd = Some(MotionCmd::jog_y(-step));
+                        }
+                        ui.label("");
+                        ui.end_row();
+                    });
+
+                ui.add_space(8.);
+
+                /// Z
+                egui::Grid::new("motion_jog_z")
+                    .spacing(Vec2::splat(2.))
+                    .show(ui, |ui| {
+                        if button(ui, "Z+").clicked() {
+                            cmd = Some(MotionCmd::jog_z(step));
+                        }
+                        ui.end_row();
+                        if button(ui, "⌂").on_hover_text("Home Z").clicked() {
+                            cmd = Some(MotionCmd::Home {
+                                x: false,
+                                y: false,
+                                z: true,
+                            });
+                        }
+                        ui.end_row();
+                        if button(ui, "Z-").clicked() {
+                            cmd = Some(MotionCmd::jog_z(-step));
+                        }
+                        ui.end_row();
+                    });
+            });
+
+            if ui.button("Home All").clicked() {
+                cmd = Some(MotionCmd::home_all());
+            }
+        });
+
+        if !idle {
+            ui.label(RichText::new("Disabled while printing").weak());
+        }
+
+        if let Some(cmd) = cmd {
+            if let Err(e) = self.send_worker_cmd(id, WorkerCmd::Motion(cmd)) {
+                error!("error sending motion command: {:?}", e);
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ use egui::ViewportBuilder;
 use egui_phosphor::fill;
 
 use super::{app::App, ui_types::GridLocation};
+use crate::conn_manager::PrinterConnCmd;
 
 /// display
 impl App {
@@ -132,12 +133,15 @@ impl App {
 
         // ui.separator();
 
+        let mut times_changed = false;
+
         if ui
             .add(egui::Slider::new(&mut self.options.times_opt.0, 0..=23).text("Wake time"))
             .changed()
         {
             self.options.times.0 =
                 chrono::NaiveTime::from_hms_opt(self.options.times_opt.0, 0, 0).unwrap();
+            times_changed = true;
         }
 
         if ui
@@ -146,6 +150,15 @@ impl App {
         {
             self.options.times.1 =
                 chrono::NaiveTime::from_hms_opt(self.options.times_opt.1, 0, 0).unwrap();
+            times_changed = true;
+        }
+
+        /// also used for notification quiet hours
+        if times_changed {
+            let (wake, bed) = self.options.times_opt;
+            if let Err(e) = self.send_cmd(PrinterConnCmd::SetQuietHours(wake, bed)) {
+                error!("failed to send quiet hours: {:?}", e);
+            }
         }
 
         ui.separator();
@@ -4,13 +4,18 @@ use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     config::{printer_config::PrinterType, printer_id::PrinterId},
-    status::GenericPrinterState,
+    conn_manager::{
+        conn_bambu::hms::{HmsMessage, HmsSeverity},
+        toolchanger::{FilamentType, ToolchangerCmd},
+        CommandResult, WorkerCmd,
+    },
+    status::{GenericPrinterState, PrinterState},
 };
 
 use super::{
     app::App,
     icons::{printer_state_icon, printer_type_icon},
-    ui_types::GridLocation,
+    ui_types::{CommandStatus, ConfirmCmd, GridLocation},
 };
 
 impl App {
@@ -104,6 +109,10 @@ impl App {
                         |ui| {
                             // printer_state_icon(ui, icon_size, &status.state);
                             printer_type_icon(ui, icon_size, printer_type);
+                            self.command_status_icon(ui, &id);
+                            if let Some(bambu) = status.state_bambu.as_ref() {
+                                Self::hms_icon(ui, &bambu.hms);
+                            }
                             ui.add(
                                 Label::new(
                                     RichText::new(&format!(
@@ -128,6 +137,9 @@ impl App {
         // resp.context_menu(|ui| {
         //     ui.label("Context menu");
         // });
+        resp.context_menu(|ui| {
+            self.printer_control_menu(ui, status, &id, printer_type);
+        });
 
         #[cfg(feature = "nope")]
         crate::ui::ui_utils::put_ui(ui, size, None, |ui| {
@@ -174,4 +186,281 @@ impl App {
 
         resp
     }
+
+    fn command_status_icon(&self, ui: &mut egui::Ui, id: &PrinterId) {
+        let Some(status) = self.command_status.get(id) else {
+            return;
+        };
+
+        match &status.result {
+            None => {
+                ui.spinner()
+                    .on_hover_text(format!("Sending: {:?}", status.cmd));
+            }
+            Some(_) if status.time.elapsed() > CommandStatus::SHOW_RESULT => {}
+            Some(CommandResult::Acked) => {
+                ui.label(RichText::new("✔").color(egui::Color32::GREEN))
+                    .on_hover_text(format!("{:?}: OK", status.cmd));
+            }
+            Some(CommandResult::Rejected(e)) => {
+                ui.label(RichText::new("⚠").color(egui::Color32::RED))
+                    .on_hover_text(format!("{:?} failed: {}", status.cmd, e));
+            }
+            Some(CommandResult::TimedOut) => {
+                ui.label(RichText::new("⚠").color(egui::Color32::YELLOW))
+                    .on_hover_text(format!("{:?}: no response from printer", status.cmd));
+            }
+        }
+
+        /// make sure the result disappears without needing input
+        if status.result.is_some() {
+            ui.ctx().request_repaint_after(CommandStatus::SHOW_RESULT);
+        }
+    }
+
+    /// MARK: HMS
+    pub(super) fn hms_color(severity: HmsSeverity) -> egui::Color32 {
+        match severity {
+            HmsSeverity::Fatal => egui::Color32::RED,
+            HmsSeverity::Serious => egui::Color32::from_rgb(251, 149, 20),
+            HmsSeverity::Common => egui::Color32::YELLOW,
+            HmsSeverity::Info => egui::Color32::LIGHT_BLUE,
+            HmsSeverity::Unknown => egui::Color32::GRAY,
+        }
+    }
+
+    /// one line per message, most severe first
+    pub(super) fn hms_list(ui: &mut egui::Ui, hms: &[HmsMessage]) {
+        let mut hms = hms.iter().collect::<Vec<_>>();
+        hms.sort_by_key(|h| std::cmp::Reverse(h.entry.severity()));
+
+        for h in hms {
+            let severity = h.entry.severity();
+            ui.label(
+                RichText::new(format!(
+                    "{} ({}, {}): {}",
+                    h.entry.code_str(),
+                    severity.to_text(),
+                    h.entry.module(),
+                    h.text()
+                ))
+                .color(Self::hms_color(severity)),
+            );
+        }
+    }
+
+    /// warning sign colored by the most severe message, nothing if there are none
+    fn hms_icon(ui: &mut egui::Ui, hms: &[HmsMessage]) {
+        let Some(worst) = hms.iter().map(|h| h.entry.severity()).max() else {
+            return;
+        };
+        ui.label(
+            RichText::new(format!("⚠{}", hms.len()))
+                .strong()
+                .color(Self::hms_color(worst)),
+        )
+        .on_hover_ui(|ui| Self::hms_list(ui, hms));
+    }
+
+    /// the current file, replaced by the most severe HMS message while there is one
+    pub(super) fn bambu_title(ui: &mut egui::Ui, status: &GenericPrinterState, size: f32) {
+        let file = status.current_file.as_deref().unwrap_or("--");
+
+        let Some(bambu) = status.state_bambu.as_ref() else {
+            ui.add(Label::new(RichText::new(file).strong().size(size)).truncate());
+            return;
+        };
+        let Some(worst) = bambu.worst_hms() else {
+            ui.add(Label::new(RichText::new(file).strong().size(size)).truncate());
+            return;
+        };
+
+        let color = Self::hms_color(worst.entry.severity());
+        ui.add(
+            Label::new(
+                RichText::new(format!("⚠ {}", worst.text()))
+                    .strong()
+                    .size(size)
+                    .color(color),
+            )
+            .truncate(),
+        )
+        .on_hover_ui(|ui| {
+            ui.label(RichText::new(file).strong());
+            ui.separator();
+            Self::hms_list(ui, &bambu.hms);
+        });
+    }
+
+    /// MARK: Controls
+    fn printer_control_menu(
+        &self,
+        ui: &mut egui::Ui,
+        status: &GenericPrinterState,
+        id: &PrinterId,
+        printer_type: PrinterType,
+    ) {
+        let send = |cmd: WorkerCmd| {
+            if let Err(e) = self.send_worker_cmd(id, cmd) {
+                error!("error sending command: {:?}", e);
+            }
+        };
+
+        let active = matches!(status.state, PrinterState::Printing | PrinterState::Paused);
+
+        if ui
+            .add_enabled(
+                status.state == PrinterState::Printing,
+                egui::Button::new("Pause"),
+            )
+            .clicked()
+        {
+            send(WorkerCmd::Pause);
+            ui.close();
+        }
+        if ui
+            .add_enabled(
+                status.state == PrinterState::Paused,
+                egui::Button::new("Resume"),
+            )
+            .clicked()
+        {
+            send(WorkerCmd::Resume);
+            ui.close();
+        }
+        if ui
+            .add_enabled(active, egui::Button::new("Cancel"))
+            .clicked()
+        {
+            self.confirm(
+                id,
+                "Cancel the current print?",
+                "Cancel Print",
+                ConfirmCmd::Worker(WorkerCmd::Cancel),
+            );
+            ui.close();
+        }
+
+        if printer_type == PrinterType::Bambu {
+            ui.separator();
+            let light = status
+                .state_bambu
+                .as_ref()
+                .and_then(|b| b.chamber_light)
+                .unwrap_or(false);
+            let text = if light { "Light Off" } else { "Light On" };
+            if ui.button(text).clicked() {
+                send(WorkerCmd::SetLight(!light));
+                ui.close();
+            }
+        }
+
+        if matches!(printer_type, PrinterType::Bambu | PrinterType::Prusa) {
+            ui.separator();
+            if ui.button("Files...").clicked() {
+                self.open_file_browser(id);
+                ui.close();
+            }
+        }
+
+        ui.separator();
+        if ui.button("Motion Controls...").clicked() {
+            self.open_controls.insert(id.clone());
+            ui.close();
+        }
+
+        if printer_type == PrinterType::Octoprint {
+            ui.separator();
+            if ui.button("Terminal...").clicked() {
+                self.terminals.entry(id.clone()).or_default();
+                ui.close();
+            }
+        }
+
+        if printer_type == PrinterType::Klipper {
+            ui.separator();
+            let estop =
+                egui::Button::new(RichText::new("Emergency Stop").color(egui::Color32::RED));
+            if ui.add(estop).clicked() {
+                self.confirm(
+                    id,
+                    "Emergency stop? Klipper will need a firmware restart afterwards.",
+                    "Emergency Stop",
+                    ConfirmCmd::Worker(WorkerCmd::EmergencyStop),
+                );
+                ui.close();
+            }
+            if ui
+                .add_enabled(!active, egui::Button::new("Firmware Restart"))
+                .clicked()
+            {
+                send(WorkerCmd::FirmwareRestart);
+                ui.close();
+            }
+        }
+    }
+
+    /// MARK: Tool menu
+    /// right click on a tool of a toolchanger
+    pub(super) fn tool_menu(
+        &self,
+        ui: &mut egui::Ui,
+        status: &GenericPrinterState,
+        id: &PrinterId,
+        tool: usize,
+    ) {
+        let send = |cmd: ToolchangerCmd| {
+            if let Err(e) = self.send_worker_cmd(id, WorkerCmd::Toolchanger(cmd)) {
+                error!("error sending command: {:?}", e);
+            }
+        };
+
+        /// tool changes in the middle of a print would ruin it
+        let idle = !matches!(status.state, PrinterState::Printing | PrinterState::Paused);
+
+        ui.label(RichText::new(format!("T{}", tool)).strong());
+        ui.separator();
+
+        if ui
+            .add_enabled(
+                idle && status.current_tool != Some(tool),
+                egui::Button::new("Pick Up"),
+            )
+            .clicked()
+        {
+            send(ToolchangerCmd::PickupTool { tool });
+            ui.close();
+        }
+        if ui
+            .add_enabled(idle, egui::Button::new("Park Tool"))
+            .clicked()
+        {
+            send(ToolchangerCmd::ParkTool);
+            ui.close();
+        }
+
+        ui.separator();
+
+        ui.add_enabled_ui(idle, |ui| {
+            ui.menu_button("Load Filament", |ui| {
+                for filament in FilamentType::presets() {
+                    let text = match filament.default_temp() {
+                        Some(temp) => format!("{} ({:.0}°C)", filament, temp),
+                        None => filament.to_string(),
+                    };
+                    if ui.button(text).clicked() {
+                        send(ToolchangerCmd::load(tool, filament));
+                        ui.close();
+                    }
+                }
+            });
+        });
+        if ui
+            .add_enabled(idle, egui::Button::new("Unload Filament"))
+            .clicked()
+        {
+            send(ToolchangerCmd::UnloadFilament { tool });
+            ui.close();
+        }
+    }
 }
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::{Color32, RichText};
+
+use crate::{config::printer_id::PrinterId, conn_manager::WorkerCmd};
+
+use super::app::App;
+
+/// MARK: Terminal
+impl App {
+    pub(super) fn show_terminals(&mut self, ctx: &egui::Context) {
+        let ids = self
+            .terminals
+            .iter()
+            .map(|t| t.key().clone())
+            .collect::<Vec<_>>();
+
+        for id in ids {
+            if !self.show_terminal(ctx, &id) {
+                self.terminals.remove(&id);
+            }
+        }
+    }
+
+    /// returns false once the window is closed
+    fn show_terminal(&self, ctx: &egui::Context, id: &PrinterId) -> bool {
+        let Some(printer) = self.config.get_printer(id) else {
+            return false;
+        };
+        let Some(mut window) = self.terminals.get_mut(id) else {
+            return false;
+        };
+        let lines = self
+            .printer_states
+            .get(id)
+            .map(|s| s.terminal.lines(window.messages_only))
+            .unwrap_or_default();
+
+        let mut open = true;
+        let mut send = None;
+        let mut clear = false;
+
+        egui::Window::new(format!("Terminal: {}", printer.name_blocking()))
+            .id(egui::Id::new(("terminal", id.inner())))
+            .default_width(520.)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut window.messages_only, "Printer messages only");
+                    if ui.button("Clear").clicked() {
+                        clear = true;
+                    }
+                });
+
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::vertical()
+                    .max_height(320.)
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                        for line in &lines[rows] {
+                            /// commands we sent, as opposed to replies
+                            let color = if line.text.starts_with("Send:") {
+                                Color32::GRAY
+                            } else {
+                                ui.style().visuals.text_color()
+                            };
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} {}",
+                                    line.time.format("%H:%M:%S"),
+                                    line.text
+                                ))
+                                .monospace()
+                                .color(color),
+                            );
+                        }
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let resp = ui.add(
+                        egui::TextEdit::singleline(&mut window.input)
+                            .hint_text("G-code")
+                            .desired_width(ui.available_width() - 60.),
+                    );
+                    let enter = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (enter || ui.button("Send").clicked()) && !window.input.trim().is_empty() {
+                        send = Some(std::mem::take(&mut window.input));
+                        resp.request_focus();
+                    }
+                });
+            });
+
+        drop(window);
+
+        if clear {
+            if let Some(state) = self.printer_states.get(id) {
+                state.terminal.clear();
+            }
+        }
+        if let Some(gcode) = send {
+            if let Err(e) = self.send_worker_cmd(id, WorkerCmd::Gcode(gcode)) {
+                error!("error sending G-code: {:?}", e);
+            }
+        }
+
+        open
+    }
+}
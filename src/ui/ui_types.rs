@@ -25,6 +25,7 @@ impl StatusColors {
 #[derive(PartialEq, Deserialize, Serialize)]
 pub enum Tab {
     Dashboard,
+    Events,
     QuickView,
     Graphs,
     Printers,
@@ -88,8 +89,9 @@ impl WebcamTexture {
 #[derive(Default, Clone, Deserialize, Serialize)]
 // #[derive(Default, Clone)]
 pub struct ThumbnailMap {
+    /// the file each pending fetch is for
     #[serde(skip)]
-    in_progress: HashSet<PrinterId>,
+    in_progress: HashMap<PrinterId, String>,
     thumbnails: HashMap<PrinterId, (String, Vec<u8>)>,
 }
 
@@ -106,19 +108,230 @@ impl ThumbnailMap {
         self.thumbnails.insert(printer_id, thumbnail);
     }
 
-    pub fn is_in_progress(&self, printer_id: &PrinterId) -> bool {
-        self.in_progress.contains(printer_id)
+    /// a fetch for a different file doesn't count, so a failed fetch
+    /// doesn't block the next file's thumbnail
+    pub fn is_in_progress(&self, printer_id: &PrinterId, file: &str) -> bool {
+        self.in_progress.get(printer_id).is_some_and(|f| f == file)
     }
 
-    pub fn set_in_progress(&mut self, printer_id: PrinterId, in_progress: bool) {
-        if in_progress {
-            self.in_progress.insert(printer_id);
-        } else {
-            self.in_progress.remove(&printer_id);
+    pub fn set_in_progress(&mut self, printer_id: PrinterId, file: &str) {
+        self.in_progress.insert(printer_id, file.to_string());
+    }
+
+    pub fn clear_in_progress(&mut self, printer_id: &PrinterId) {
+        self.in_progress.remove(printer_id);
+    }
+}
+
+/// last command sent to a printer, shown in the printer header
+#[derive(Debug, Clone)]
+pub struct CommandStatus {
+    pub id: crate::conn_manager::CommandId,
+    pub cmd: crate::conn_manager::WorkerCmd,
+    /// None while waiting for the printer
+    pub result: Option<crate::conn_manager::CommandResult>,
+    /// when the command was sent, or when the result arrived
+    pub time: std::time::Instant,
+}
+
+impl CommandStatus {
+    /// how long to keep showing a result
+    pub const SHOW_RESULT: std::time::Duration = std::time::Duration::from_secs(10);
+
+    pub fn new(id: crate::conn_manager::CommandId, cmd: crate::conn_manager::WorkerCmd) -> Self {
+        Self {
+            id,
+            cmd,
+            result: None,
+            time: std::time::Instant::now(),
         }
     }
 }
 
+/// a command that can't be undone, sent once the user confirms it
+#[derive(Debug, Clone)]
+pub struct ConfirmDialog {
+    /// e.g. "Cancel the current print?"
+    pub text: String,
+    /// the confirm button, e.g. "Cancel Print"
+    pub button: String,
+    pub cmd: ConfirmCmd,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfirmCmd {
+    Worker(crate::conn_manager::WorkerCmd),
+    Files(crate::conn_manager::files::FileCmd),
+}
+
+/// open "Edit AMS Slot" window
+#[derive(Debug, Clone)]
+pub struct AmsSlotEditor {
+    pub printer_id: PrinterId,
+    pub setting: crate::conn_manager::conn_bambu::command::ChangeAMSFilamentSetting,
+}
+
+/// open "Files" window, filled in by PrinterConnMsg::Files
+#[derive(Debug, Clone)]
+pub struct FileBrowser {
+    pub printer_id: PrinterId,
+    pub dir: String,
+    pub files: Vec<crate::conn_manager::files::RemoteFile>,
+    /// waiting on a reply
+    pub loading: bool,
+    /// last result or error
+    pub status: Option<String>,
+    pub upload_path: String,
+    pub download_dir: String,
+    /// file selected with "Print", and its AMS mapping as typed
+    pub print: Option<(
+        crate::conn_manager::conn_bambu::command::ProjectFile,
+        String,
+    )>,
+}
+
+impl FileBrowser {
+    pub fn new(printer_id: PrinterId, dir: &str) -> Self {
+        Self {
+            printer_id,
+            dir: dir.to_string(),
+            files: vec![],
+            loading: true,
+            status: None,
+            upload_path: String::new(),
+            download_dir: ".".to_string(),
+            print: None,
+        }
+    }
+}
+
+/// open "Terminal" window for one printer, the lines are in GenericPrinterState::terminal
+#[derive(Debug, Clone, Default)]
+pub struct TerminalWindow {
+    pub input: String,
+    /// only the printer's messages, without sent commands
+    pub messages_only: bool,
+}
+
+/// jog controls in the fullscreen printer view
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MotionPanel {
+    /// mm per jog
+    pub step: f64,
+}
+
+impl Default for MotionPanel {
+    fn default() -> Self {
+        Self { step: 10. }
+    }
+}
+
+impl MotionPanel {
+    pub const STEPS: [f64; 4] = [0.1, 1., 10., 50.];
+}
+
+/// settings for the Graphs tab
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GraphsView {
+    pub printer: Option<PrinterId>,
+    pub window_mins: u32,
+}
+
+impl Default for GraphsView {
+    fn default() -> Self {
+        Self {
+            printer: None,
+            window_mins: 30,
+        }
+    }
+}
+
+/// state for the Events tab
+#[derive(Debug)]
+pub struct EventsView {
+    /// None = all printers
+    pub printer: Option<PrinterId>,
+    pub text: String,
+    pub range: EventRange,
+    pub page: i64,
+    pub page_size: i64,
+    /// most recent query sent, older replies are ignored
+    pub last_query: Option<crate::error_logging::error_db::ErrorQuery>,
+    pub results: Option<crate::error_logging::error_db::ErrorPage>,
+    pub error: Option<String>,
+    /// waiting on the conn manager
+    pub loading: bool,
+    pub export_format: crate::error_logging::error_db::ExportFormat,
+    pub export_path: String,
+    pub export_status: Option<String>,
+}
+
+impl Default for EventsView {
+    fn default() -> Self {
+        Self {
+            printer: None,
+            text: String::new(),
+            range: EventRange::Week,
+            page: 0,
+            page_size: 50,
+            last_query: None,
+            results: None,
+            error: None,
+            loading: false,
+            export_format: crate::error_logging::error_db::ExportFormat::Csv,
+            export_path: "events.csv".to_string(),
+            export_status: None,
+        }
+    }
+}
+
+impl EventsView {
+    pub fn query(&self) -> crate::error_logging::error_db::ErrorQuery {
+        crate::error_logging::error_db::ErrorQuery {
+            printer_id: self.printer.as_ref().map(|id| id.inner().to_string()),
+            since: self.range.since(),
+            until: None,
+            text: Some(self.text.trim().to_string()).filter(|t| !t.is_empty()),
+            limit: self.page_size,
+            offset: self.page * self.page_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventRange {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl EventRange {
+    pub const ALL: [Self; 4] = [Self::Day, Self::Week, Self::Month, Self::All];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventRange::Day => "Last 24 hours",
+            EventRange::Week => "Last 7 days",
+            EventRange::Month => "Last 30 days",
+            EventRange::All => "All time",
+        }
+    }
+
+    /// UTC, to match the database
+    pub fn since(&self) -> Option<chrono::NaiveDateTime> {
+        let days = match self {
+            EventRange::Day => 1,
+            EventRange::Week => 7,
+            EventRange::Month => 30,
+            EventRange::All => return None,
+        };
+        Some((chrono::Utc::now() - chrono::Duration::days(days)).naive_utc())
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GridLocation {
     pub col: usize,
@@ -322,21 +322,7 @@ impl App {
                         .with_cross_align(egui::Align::Min);
 
                     ui.with_layout(layout, |ui| {
-                        ui.add(
-                            Label::new(
-                                RichText::new(&format!(
-                                    "{}",
-                                    status
-                                        .current_file
-                                        .as_ref()
-                                        .map(|s| s.as_str())
-                                        .unwrap_or("--"),
-                                ))
-                                .strong()
-                                .size(text_size_title),
-                            )
-                            .truncate(),
-                        );
+                        Self::bambu_title(ui, &status, text_size_title);
                     });
                 });
 
@@ -518,7 +504,7 @@ pub fn paint_ams(
     size: f32,
     // size: f32,
     ams: &AmsStatus,
-) {
+) -> Option<(i64, i64)> {
     #[cfg(feature = "nope")]
     ui.vertical(|ui| {
         ui.label(&format!("current tray: {:?}", ams.current_tray));
@@ -534,7 +520,14 @@ pub fn paint_ams(
     // debug!("size = {:#?}", size);
 
     let size = Vec2::new(ui.available_width(), size);
-    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let (response, painter) = ui.allocate_painter(size, Sense::click());
+
+    /// (ams_id, tray_id) of the clicked slot
+    let click_pos = response
+        .clicked()
+        .then(|| response.interact_pointer_pos())
+        .flatten();
+    let mut clicked = None;
 
     let rect = response.rect;
     let c = rect.center();
@@ -572,7 +565,7 @@ pub fn paint_ams(
 
     if num_units == 0 {
         // error!("No units found in ams status");
-        return;
+        return None;
     } else if num_units == 1 {
         // let unit = &ams.units[&0];
         // let Some(unit) = ams.units.get(&0) else {
@@ -585,7 +578,7 @@ pub fn paint_ams(
 
         let Some((k, unit)) = ams.units.iter().next() else {
             error!("No AMS units found?");
-            return;
+            return None;
         };
 
         let edge_padding = rect.width() / 8.0;
@@ -598,6 +591,10 @@ pub fn paint_ams(
             let c = p0 + Vec2::new(x, y);
             // debug!("c: {:#?}", c);
 
+            if click_pos.is_some_and(|p| p.distance(c) <= circle_r) {
+                clicked = Some((*k, slot_idx as i64));
+            }
+
             match &unit.slots[slot_idx] {
                 Some(slot) => {
                     painter.circle(
@@ -641,6 +638,10 @@ pub fn paint_ams(
 
                 let c = Pos2::new(x, y);
 
+                if click_pos.is_some_and(|p| p.distance(c) <= small_circle_r) {
+                    clicked = Some((unit, slot_idx as i64));
+                }
+
                 match &ams.units[&unit].slots[slot_idx] {
                     Some(slot) => {
                         // painter.circle_filled(c, circle_r, slot.color);
@@ -696,7 +697,7 @@ pub fn paint_ams(
         debug!("ams.units.len() = {:#?}", ams.units.len());
     }
 
-    //
+    clicked
 }
 
 fn draw_ams_current(
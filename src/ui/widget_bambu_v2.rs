@@ -10,10 +10,14 @@ use super::{
     icons::{
         icon_menu_with_size, printer_state_icon, thumbnail_bed, thumbnail_chamber, thumbnail_nozzle,
     },
-    ui_types::GridLocation,
+    ui_types::{GridLocation, PreviewType, ThumbnailMap},
 };
 use crate::{
-    config::printer_config::{PrinterConfigBambu, PrinterType},
+    config::{
+        printer_config::{PrinterConfigBambu, PrinterType},
+        printer_id::PrinterId,
+    },
+    conn_manager::PrinterConnCmd,
     status::{
         bambu_status::{h2d_extruder::ExtruderSwitchState, AmsStatus, BambuPrinterType},
         GenericPrinterState,
@@ -58,6 +62,7 @@ impl App {
         let thumbnail_height = thumbnail_width * 0.5625;
 
         drop(status);
+        let mut ams_clicked = None;
         ui.spacing_mut().item_spacing.x = 1.;
         egui_extras::StripBuilder::new(ui)
             .clip(true)
@@ -206,10 +211,42 @@ impl App {
                         //
                     });
                 } else {
+                    let preview_key = status.state_bambu.as_ref().and_then(|b| b.thumbnail_key());
+
                     /// thumbnail/webcam
                     strip.cell(|ui| {
                         // ui.label("Webcam: TODO");
 
+                        let size = Vec2::new(thumbnail_width, thumbnail_height);
+
+                        /// right click switches between the plate preview and the webcam
+                        let preview = self
+                            .preview_setting
+                            .entry(printer.id.clone())
+                            .or_insert(PreviewType::Webcam);
+                        if matches!(preview, PreviewType::Thumbnail) {
+                            let resp = bambu_print_preview(
+                                ui,
+                                &mut self.thumbnails,
+                                self.cmd_tx.as_ref(),
+                                &printer.id,
+                                preview_key,
+                                size,
+                            );
+                            match resp {
+                                Some(resp) if resp.clicked_by(egui::PointerButton::Secondary) => {
+                                    preview.toggle_type();
+                                }
+                                Some(_) => {}
+                                None => {
+                                    if ui.button("Show webcam").clicked() {
+                                        preview.toggle_type();
+                                    }
+                                }
+                            }
+                            return;
+                        }
+
                         let mut entry = self
                             .webcam_textures
                             .entry(printer.id.clone())
@@ -226,8 +263,6 @@ impl App {
                                 super::ui_types::WebcamTexture::new(texture)
                             });
 
-                        let size = Vec2::new(thumbnail_width, thumbnail_height);
-
                         if entry.enabled.load(Ordering::SeqCst) {
                             let img = egui::Image::from_texture((entry.texture.id(), size))
                                 .fit_to_exact_size(size)
@@ -240,6 +275,8 @@ impl App {
                             if resp.clicked_by(egui::PointerButton::Primary) {
                                 // debug!("webcam clicked");
                                 self.selected_stream = Some(printer.id.clone());
+                            } else if resp.clicked_by(egui::PointerButton::Secondary) {
+                                preview.toggle_type();
                             }
                         } else if self.options.auto_start_streams {
                             if let Err(e) = self.stream_cmd_tx.as_ref().unwrap().send(
@@ -259,6 +296,9 @@ impl App {
                             // entry.enabled = true;
                             entry.enabled.store(true, Ordering::SeqCst);
                         } else {
+                            if preview_key.is_some() && ui.button("Show print preview").clicked() {
+                                preview.toggle_type();
+                            }
                             if ui.button("Enable webcam").clicked() {
                                 self.stream_cmd_tx
                                     .as_ref()
@@ -674,21 +714,7 @@ impl App {
                         .with_cross_align(egui::Align::Min);
 
                     ui.with_layout(layout, |ui| {
-                        ui.add(
-                            Label::new(
-                                RichText::new(&format!(
-                                    "{}",
-                                    status
-                                        .current_file
-                                        .as_ref()
-                                        .map(|s| s.as_str())
-                                        .unwrap_or("--"),
-                                ))
-                                .strong()
-                                .size(text_size_title),
-                            )
-                            .truncate(),
-                        );
+                        Self::bambu_title(ui, &status, text_size_title);
                     });
                 });
 
@@ -825,7 +851,7 @@ impl App {
                     let height = 44.;
 
                     if bambu_type == Some(BambuPrinterType::H2D) {
-                        super::ams::paint_ams_h2d(ui, height, bambu);
+                        ams_clicked = super::ams::paint_ams_h2d(ui, height, bambu);
                         // trace!("AMS: {:#?}", bambu.ams);
                     } else {
                         // self.show_ams(ui, printer);
@@ -835,7 +861,7 @@ impl App {
                             return;
                         };
 
-                        super::widget_bambu::paint_ams(ui, height, ams);
+                        ams_clicked = super::widget_bambu::paint_ams(ui, height, ams);
                     }
                     // ui.ctx()
                     //     .debug_painter()
@@ -847,6 +873,49 @@ impl App {
 
         ui.spacing_mut().item_spacing.x = 8.;
 
+        if let Some((ams_id, tray_id)) = ams_clicked {
+            self.open_ams_editor(&printer.id, ams_id, tray_id);
+        }
+
         resp
     }
 }
+
+/// plate preview of the current print, fetched once per print.
+/// None until there's an image to show
+fn bambu_print_preview(
+    ui: &mut egui::Ui,
+    thumbnails: &mut ThumbnailMap,
+    cmd_tx: Option<&tokio::sync::mpsc::UnboundedSender<PrinterConnCmd>>,
+    id: &PrinterId,
+    key: Option<String>,
+    size: Vec2,
+) -> Option<Response> {
+    let key = key?;
+
+    match thumbnails.get(id) {
+        Some((file, img)) if file == &key => {
+            let img =
+                egui::Image::from_bytes(format!("bytes://{}/{}", id.inner(), key), img.clone())
+                    .fit_to_exact_size(size)
+                    .corner_radius(CornerRadius::same(4))
+                    .sense(Sense::click());
+            Some(ui.add(img))
+        }
+        Some(_) => {
+            thumbnails.remove(id);
+            thumbnails.clear_in_progress(id);
+            None
+        }
+        None => {
+            if !thumbnails.is_in_progress(id, &key) {
+                debug!("sending thumbnail fetch request");
+                thumbnails.set_in_progress(id.clone(), &key);
+                if let Some(tx) = cmd_tx {
+                    let _ = tx.send(PrinterConnCmd::FetchThumbnail(id.clone(), key));
+                }
+            }
+            None
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use tracing::{debug, error, info, trace, warn};
 
-use egui::{Color32, Label, Layout, Response, RichText, Vec2};
+use egui::{Color32, Label, Layout, Response, RichText, Sense, Vec2};
 
 use crate::{
-    config::printer_config::{PrinterConfigKlipper, PrinterType},
+    config::{
+        printer_config::{PrinterConfigKlipper, PrinterType},
+        printer_id::PrinterId,
+    },
     status::GenericPrinterState,
 };
 
@@ -51,6 +54,8 @@ impl App {
         let thumbnail_width = crate::ui::PRINTER_WIDGET_SIZE.0 - 24.;
         let thumbnail_height = thumbnail_width * 0.5625;
 
+        drop(status);
+
         ui.spacing_mut().item_spacing.x = 1.;
         // #[cfg(feature = "nope")]
         egui_extras::StripBuilder::new(ui)
@@ -72,22 +77,28 @@ impl App {
             .size(egui_extras::Size::exact(text_size_temps + 2.))
             // .size(egui_extras::Size::initial(10.))
             .vertical(|mut strip| {
+                /// thumbnail
+                strip.cell(|ui| {
+                    self.klipper_preview(ui, printer, (thumbnail_width, thumbnail_height));
+                });
+
                 let Some(status) = self.printer_states.get(&printer.id) else {
                     warn!("Printer not found: {:?}", printer.id);
                     panic!();
                 };
 
-                /// thumbnail/webcam
-                strip.cell(|ui| {
-                    ui.label("Thumbnail");
-                });
-
                 /// temperatures
                 strip.strip(|mut builder| {
                     let font_size = 12.;
 
                     if printer.toolchanger {
-                        self.klipper_temperatures_toolchanger(&status, layout, font_size, builder);
+                        self.klipper_temperatures_toolchanger(
+                            &status,
+                            &printer.id,
+                            layout,
+                            font_size,
+                            builder,
+                        );
                     } else {
                         self.klipper_temperatures(&status, layout, font_size, builder);
                     }
@@ -145,8 +156,7 @@ impl App {
                     );
                 });
 
-                /// ETA
-                /// TODO: layers?
+                /// ETA, layers
                 strip.strip(|mut builder| {
                     let Some(remaining) = status.time_remaining else {
                         return;
@@ -181,7 +191,6 @@ impl App {
                                 ));
                             });
 
-                            /// Prusa doesn't tell what layer it's on?
                             strip.cell(|ui| {
                                 /// TODO: status instead of layers during prepare
                                 #[cfg(feature = "nope")]
@@ -203,6 +212,14 @@ impl App {
                                     }
                                 }
 
+                                if let Some((layer, total)) = status.layer {
+                                    ui.add(Label::new(
+                                        RichText::new(format!("{}/{}", layer, total))
+                                            .strong()
+                                            .size(text_size_eta),
+                                    ));
+                                }
+
                                 #[cfg(feature = "nope")]
                                 if let (Some(layer), Some(max)) =
                                     (status.layer_num, status.total_layer_num)
@@ -308,6 +325,51 @@ impl App {
 }
 
 impl App {
+    fn klipper_preview(
+        &mut self,
+        ui: &mut egui::Ui,
+        printer: &PrinterConfigKlipper,
+        (thumbnail_width, thumbnail_height): (f32, f32),
+    ) {
+        let Some(file) = self
+            .printer_states
+            .get(&printer.id)
+            .and_then(|s| s.current_file.clone())
+            .filter(|f| !f.is_empty())
+        else {
+            return;
+        };
+
+        match self.thumbnails.get(&printer.id) {
+            Some((thumb_file, img)) => {
+                if thumb_file != &file {
+                    self.thumbnails.remove(&printer.id);
+                    self.thumbnails.clear_in_progress(&printer.id);
+                } else {
+                    /// file names aren't unique across printers
+                    let uri = format!("bytes://{}/{}", printer.id.inner(), file);
+                    let img = egui::Image::from_bytes(uri, img.clone())
+                        .fit_to_exact_size(Vec2::new(thumbnail_width, thumbnail_height));
+                    ui.add(img);
+                }
+            }
+            None => {
+                if !self.thumbnails.is_in_progress(&printer.id, &file) {
+                    debug!("sending thumbnail fetch request");
+                    self.thumbnails.set_in_progress(printer.id.clone(), &file);
+                    self.cmd_tx
+                        .as_ref()
+                        .unwrap()
+                        .send(crate::conn_manager::PrinterConnCmd::FetchThumbnail(
+                            printer.id.clone(),
+                            file,
+                        ))
+                        .unwrap();
+                }
+            }
+        }
+    }
+
     fn klipper_temperatures_row2(
         &self,
 
@@ -337,9 +399,11 @@ impl App {
             });
     }
 
+    /// right click a tool to pick it up or change its filament
     fn klipper_temperatures_toolchanger(
         &self,
         status: &GenericPrinterState,
+        id: &PrinterId,
         layout: Layout,
         _font_size: f32,
         builder: egui_extras::StripBuilder<'_>,
@@ -364,7 +428,7 @@ impl App {
                             ui.style().visuals.widgets.noninteractive.bg_stroke.color
                         };
 
-                        egui::Frame::group(ui.style())
+                        let resp = egui::Frame::group(ui.style())
                             .inner_margin(0.)
                             .outer_margin(0.)
                             // .stroke((1., ui.style().visuals.widgets.noninteractive.bg_stroke.color))
@@ -388,7 +452,13 @@ impl App {
                                     text,
                                 );
                                 // ui.label(format!("{}", i + 1));
-                            });
+                            })
+                            .response
+                            .interact(Sense::click());
+
+                        resp.context_menu(|ui| {
+                            self.tool_menu(ui, status, id, i);
+                        });
                     });
                 }
             });
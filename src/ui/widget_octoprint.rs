@@ -0,0 +1,292 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use tracing::{debug, error, info, trace, warn};
+
+use egui::{Color32, Label, Layout, Response, RichText, Vec2};
+
+use crate::{
+    config::printer_config::{PrinterConfigOcto, PrinterType},
+    status::GenericPrinterState,
+};
+
+use super::{
+    app::App,
+    icons::{thumbnail_bed, thumbnail_nozzle},
+    ui_types::GridLocation,
+};
+
+impl App {
+    pub fn show_printer_octoprint(
+        &mut self,
+        ui: &mut egui::Ui,
+        pos: GridLocation,
+        printer: &PrinterConfigOcto,
+    ) -> Response {
+        /// checked at call site
+        let Some(status) = self.printer_states.get(&printer.id) else {
+            warn!("Printer not found: {:?}", printer.id);
+            panic!();
+        };
+
+        /// Name, state, and controls button
+        /// Can't be in strip or response can't get passed up
+        let resp = self.printer_widget_header(
+            ui,
+            &status,
+            printer.id.clone(),
+            &printer.name,
+            pos,
+            PrinterType::Octoprint,
+        );
+
+        let layout = Layout::left_to_right(egui::Align::Center)
+            .with_cross_justify(true)
+            .with_main_justify(true)
+            .with_cross_align(egui::Align::Center);
+
+        let text_size_title = 12.;
+        let text_size_eta = 12.;
+
+        let thumbnail_width = crate::ui::PRINTER_WIDGET_SIZE.0 - 24.;
+        let thumbnail_height = thumbnail_width * 0.5625;
+
+        drop(status);
+
+        ui.spacing_mut().item_spacing.x = 1.;
+        egui_extras::StripBuilder::new(ui)
+            .clip(true)
+            .cell_layout(layout)
+            // thumbnail
+            .size(egui_extras::Size::exact(thumbnail_height + 6.))
+            // temperatures
+            .size(egui_extras::Size::exact(26.))
+            // Title
+            .size(egui_extras::Size::exact(text_size_title + 4.))
+            // progress bar
+            .size(egui_extras::Size::exact(26.))
+            // ETA
+            .size(egui_extras::Size::exact(text_size_eta + 2.))
+            .vertical(|mut strip| {
+                /// thumbnail
+                strip.cell(|ui| {
+                    self.octoprint_preview(ui, printer, (thumbnail_width, thumbnail_height));
+                });
+
+                let Some(status) = self.printer_states.get(&printer.id) else {
+                    warn!("Printer not found: {:?}", printer.id);
+                    panic!();
+                };
+
+                /// temperatures
+                strip.strip(|builder| {
+                    Self::octoprint_temperatures(&status, layout, 12., builder);
+                });
+
+                /// Title
+                strip.cell(|ui| {
+                    let layout = Layout::left_to_right(egui::Align::Min)
+                        .with_cross_justify(true)
+                        .with_main_justify(true)
+                        .with_cross_align(egui::Align::Min);
+
+                    /// the current file is a path relative to the uploads folder
+                    let name = status
+                        .current_file
+                        .as_deref()
+                        .filter(|f| !f.is_empty())
+                        .map(|f| f.rsplit('/').next().unwrap_or(f))
+                        .unwrap_or("--");
+
+                    ui.with_layout(layout, |ui| {
+                        ui.add(
+                            Label::new(RichText::new(name).strong().size(text_size_title))
+                                .truncate(),
+                        );
+                    });
+                });
+
+                /// progress bar
+                strip.cell(|ui| {
+                    let p = status.progress;
+                    ui.add(
+                        egui::ProgressBar::new(p / 100.0)
+                            .desired_width(ui.available_width())
+                            .text(format!("{:.0}%", p)),
+                    );
+                });
+
+                /// ETA
+                strip.strip(|builder| {
+                    let Some(remaining) = status.time_remaining else {
+                        return;
+                    };
+
+                    let time_finish = chrono::Local::now() + remaining;
+
+                    builder
+                        .size(egui_extras::Size::relative(0.3))
+                        .size(egui_extras::Size::remainder())
+                        .size(egui_extras::Size::relative(0.3))
+                        .horizontal(|mut strip| {
+                            strip.cell(|ui| {
+                                ui.add(Label::new(
+                                    RichText::new(time_finish.format("%-I:%M %p").to_string())
+                                        .strong()
+                                        .size(text_size_eta),
+                                ));
+                            });
+
+                            strip.cell(|ui| {
+                                if let Some(printing) = status.time_printing {
+                                    ui.add(Label::new(
+                                        RichText::new(format!(
+                                            "{:02}:{:02}",
+                                            printing.num_hours(),
+                                            printing.num_minutes() % 60
+                                        ))
+                                        .size(text_size_eta),
+                                    ));
+                                }
+                            });
+
+                            strip.cell(|ui| {
+                                ui.add(Label::new(
+                                    RichText::new(format!(
+                                        "-{:02}:{:02}",
+                                        remaining.num_hours(),
+                                        remaining.num_minutes() % 60
+                                    ))
+                                    .strong()
+                                    .size(text_size_eta),
+                                ));
+                            });
+                        });
+                });
+            });
+
+        resp
+    }
+}
+
+impl App {
+    fn octoprint_preview(
+        &mut self,
+        ui: &mut egui::Ui,
+        printer: &PrinterConfigOcto,
+        (thumbnail_width, thumbnail_height): (f32, f32),
+    ) {
+        let Some(file) = self
+            .printer_states
+            .get(&printer.id)
+            .and_then(|s| s.current_file.clone())
+            .filter(|f| !f.is_empty())
+        else {
+            return;
+        };
+
+        match self.thumbnails.get(&printer.id) {
+            Some((thumb_file, img)) => {
+                if thumb_file != &file {
+                    self.thumbnails.remove(&printer.id);
+                    self.thumbnails.clear_in_progress(&printer.id);
+                } else {
+                    /// file names aren't unique across printers
+                    let uri = format!("bytes://{}/{}", printer.id.inner(), file);
+                    let img = egui::Image::from_bytes(uri, img.clone())
+                        .fit_to_exact_size(Vec2::new(thumbnail_width, thumbnail_height));
+                    ui.add(img);
+                }
+            }
+            None => {
+                /// only tried once per file, most servers have no thumbnail plugin
+                if !self.thumbnails.is_in_progress(&printer.id, &file) {
+                    debug!("sending thumbnail fetch request");
+                    self.thumbnails.set_in_progress(printer.id.clone(), &file);
+                    self.cmd_tx
+                        .as_ref()
+                        .unwrap()
+                        .send(crate::conn_manager::PrinterConnCmd::FetchThumbnail(
+                            printer.id.clone(),
+                            file,
+                        ))
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    /// one nozzle, or each tool if there's more than one
+    fn octoprint_temperatures(
+        status: &GenericPrinterState,
+        layout: Layout,
+        font_size: f32,
+        builder: egui_extras::StripBuilder<'_>,
+    ) {
+        let mut tools = status.nozzle_temps.keys().copied().collect::<Vec<_>>();
+        tools.sort();
+        if tools.len() < 2 {
+            tools.clear();
+        }
+
+        let temp_text = |temp: f32, target: f32| {
+            let text = RichText::new(format!("{:.1}°C / {}", temp, target as i64))
+                .strong()
+                .size(font_size);
+            if target > 0. {
+                text.color(Color32::from_rgb(251, 149, 20))
+            } else {
+                text
+            }
+        };
+
+        builder
+            .sizes(egui_extras::Size::remainder(), tools.len().max(1) + 1)
+            .cell_layout(layout)
+            .horizontal(|mut strip| {
+                if tools.is_empty() {
+                    strip.cell(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(thumbnail_nozzle(status.nozzle_temp_target > 0.));
+                            ui.add(
+                                Label::new(temp_text(
+                                    status.nozzle_temp,
+                                    status.nozzle_temp_target,
+                                ))
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                for t in tools.iter() {
+                    let temp = status.nozzle_temps.get(t).copied().unwrap_or(0.);
+                    let target = status.nozzle_temps_target.get(t).copied().unwrap_or(0.);
+                    strip.cell(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("T{}", t)).size(font_size));
+                            ui.add(
+                                Label::new(
+                                    RichText::new(format!("{:.0}°C", temp))
+                                        .strong()
+                                        .size(font_size)
+                                        .color(if target > 0. {
+                                            Color32::from_rgb(251, 149, 20)
+                                        } else {
+                                            ui.style().visuals.text_color()
+                                        }),
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                strip.cell(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(thumbnail_bed(status.bed_temp_target > 0.));
+                        ui.add(
+                            Label::new(temp_text(status.bed_temp, status.bed_temp_target))
+                                .truncate(),
+                        );
+                    });
+                });
+            });
+    }
+}
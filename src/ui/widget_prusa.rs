@@ -72,7 +72,7 @@ impl App {
             .size(egui_extras::Size::exact(26.))
             // ETA
             .size(egui_extras::Size::exact(text_size_eta + 2.))
-            // Tools (Placeholder)
+            // Tool temperatures, right click for the tool menu
             .size(egui_extras::Size::exact(text_size_temps + 6.))
             // .size(egui_extras::Size::exact(text_size_temps + 2.))
             .size(egui_extras::Size::exact(text_size_temps + 6.))
@@ -298,14 +298,17 @@ impl App {
                         });
                 });
 
+                /// reported by the OctoPrint connection, the XL has up to 5
+                let tools = status.nozzle_temps.len().max(1);
+
                 /// Tool Temperatures
                 strip.strip(|mut builder| {
                     builder
-                        .sizes(egui_extras::Size::relative(0.2), 5)
+                        .sizes(egui_extras::Size::relative(1. / tools as f32), tools)
                         .horizontal(|mut strip| {
-                            for idx in 0..5 {
+                            for idx in 0..tools {
                                 strip.cell(|ui| {
-                                    ui.add(
+                                    let resp = ui.add(
                                         Label::new(
                                             RichText::new(format!(
                                                 "{:.1}°C",
@@ -314,8 +317,14 @@ impl App {
                                             .strong()
                                             .size(text_size_temps),
                                         )
-                                        .truncate(),
+                                        .truncate()
+                                        .sense(Sense::click()),
                                     );
+                                    if printer.toolchanger {
+                                        resp.context_menu(|ui| {
+                                            self.tool_menu(ui, &status, &printer.id, idx);
+                                        });
+                                    }
                                 });
                             }
                         });
@@ -324,9 +333,9 @@ impl App {
                 /// Tool Target Temperatures
                 strip.strip(|mut builder| {
                     builder
-                        .sizes(egui_extras::Size::relative(0.2), 5)
+                        .sizes(egui_extras::Size::relative(1. / tools as f32), tools)
                         .horizontal(|mut strip| {
-                            for idx in 0..5 {
+                            for idx in 0..tools {
                                 strip.cell(|ui| {
                                     ui.add(
                                         Label::new(
@@ -392,7 +401,7 @@ impl App {
                         if file != thumbnail_path {
                             // debug!("bad thumbnail");
                             self.thumbnails.remove(&printer.id);
-                            self.thumbnails.set_in_progress(printer.id.clone(), false);
+                            self.thumbnails.clear_in_progress(&printer.id);
                         } else {
                             // ui.label("Thumbnail");
                             let img =
@@ -409,9 +418,10 @@ impl App {
                         }
                     }
                     None => {
-                        if !self.thumbnails.is_in_progress(&printer.id) {
+                        if !self.thumbnails.is_in_progress(&printer.id, thumbnail_path) {
                             debug!("sending thumbnail fetch request");
-                            self.thumbnails.set_in_progress(printer.id.clone(), true);
+                            self.thumbnails
+                                .set_in_progress(printer.id.clone(), thumbnail_path);
                             self.cmd_tx
                                 .as_ref()
                                 .unwrap()